@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE quests DROP COLUMN almost_count;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE quests ADD COLUMN almost_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE quest_patterns (
+    id INTEGER PRIMARY KEY NOT NULL,
+    quest_id INTEGER NOT NULL,
+    pattern VARCHAR NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (quest_id) REFERENCES quests(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE quests DROP COLUMN reverse_miss_count;
+ALTER TABLE quests DROP COLUMN reverse_ok_count;
+ALTER TABLE quests DROP COLUMN reverse_display_count;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE quests ADD COLUMN reverse_display_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE quests ADD COLUMN reverse_ok_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE quests ADD COLUMN reverse_miss_count INTEGER NOT NULL DEFAULT 0;
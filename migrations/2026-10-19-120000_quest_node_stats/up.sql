@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE quest_node_stats (
+    id INTEGER PRIMARY KEY NOT NULL,
+    quest_id INTEGER NOT NULL,
+    node VARCHAR NOT NULL,
+    ok_count INTEGER NOT NULL DEFAULT 0,
+    miss_count INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (quest_id) REFERENCES quests(id) ON DELETE CASCADE,
+    UNIQUE (quest_id, node)
+);
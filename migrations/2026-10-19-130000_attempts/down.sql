@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE quests DROP COLUMN due_at;
+ALTER TABLE quests DROP COLUMN interval_days;
+DROP TABLE attempts;
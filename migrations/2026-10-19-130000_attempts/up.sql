@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE attempts (
+    id INTEGER PRIMARY KEY NOT NULL,
+    quest_id INTEGER NOT NULL,
+    mode VARCHAR NOT NULL,
+    grade VARCHAR NOT NULL,
+    first_key_ms INTEGER,
+    submit_ms INTEGER NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (quest_id) REFERENCES quests(id) ON DELETE CASCADE
+);
+
+ALTER TABLE quests ADD COLUMN interval_days INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE quests ADD COLUMN due_at DATETIME;
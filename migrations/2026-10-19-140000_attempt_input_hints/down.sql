@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE attempts DROP COLUMN hints_used;
+ALTER TABLE attempts DROP COLUMN input;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE attempts ADD COLUMN input VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE attempts ADD COLUMN hints_used INTEGER NOT NULL DEFAULT 0;
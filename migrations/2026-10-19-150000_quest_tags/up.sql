@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE quest_tags (
+    id INTEGER PRIMARY KEY NOT NULL,
+    quest_id INTEGER NOT NULL,
+    tag VARCHAR NOT NULL,
+    FOREIGN KEY (quest_id) REFERENCES quests(id) ON DELETE CASCADE,
+    UNIQUE (quest_id, tag)
+);
@@ -1,12 +1,13 @@
 use std::io::{self, Read};
 use anyhow::Result;
-use crate::db::schema::quests;
 use crate::db::models::NewQuest;
-use diesel::RunQueryDsl;
-use diesel;
+use crate::db::quests::insert_quest;
 use diesel::SqliteConnection;
 use crossterm;
-use crate::window::Screen;
+use crate::window::{highlight_input, print_cells, queue_cells, Action, Screen, Theme, ANSWER_ACTIONS};
+use crate::GhiConfig;
+use crate::parser::parser::{to_pattern, CmdParser};
+use crate::parser::lint::{lint, Level};
 
 fn get_default_lesson(conn: &mut SqliteConnection) -> i32 {
     use diesel::prelude::*;
@@ -15,35 +16,48 @@ fn get_default_lesson(conn: &mut SqliteConnection) -> i32 {
     default_id
 }
 
-fn read_input() -> Result<String> {
-    use crossterm::{terminal, event, execute};
+// Draws the typed line again. A pattern is coloured as it is typed, so a
+// mistake shows up before it is entered.
+fn redraw(stdout: &mut std::io::Stdout, answer: &str, theme: Option<&Theme>) -> Result<()> {
+    use crossterm::{terminal, execute};
     use std::io::Write;
 
+    execute!(stdout, terminal::Clear(terminal::ClearType::CurrentLine))?;
+    print!("\r");
+    if let Some(theme) = theme {
+        queue_cells(stdout, &highlight_input(answer, theme), true)?;
+    } else {
+        print!("{answer}");
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn read_input(config: &GhiConfig, is_pattern: bool) -> Result<String> {
+    use crossterm::event;
+
     let mut stdout = std::io::stdout();
     let mut page = Screen::start()?;
 
+    let theme = if is_pattern && config.color { Some(&config.theme) } else { None };
     let mut answer = String::new();
     while !page.get_quit() {
         while event::poll(std::time::Duration::ZERO)? {
             match event::read()? {
                 event::Event::Key(ev) if ev.kind == event::KeyEventKind::Press => {
-                    match ev.code {
-                        event::KeyCode::Char(ch) => {
-                            if ev.modifiers.contains(event::KeyModifiers::CONTROL) && ch == 'c' {
-                                page.set_quit();
-                                std::process::exit(1);
-                            } else {
-                                print!("{ch}");
-                                stdout.flush()?;
-                                answer.push(ch);
-                            }
+                    match (config.keys.action(&ev, &ANSWER_ACTIONS), ev.code) {
+                        (Some(Action::Quit), _) => {
+                            page.set_quit();
+                            std::process::exit(1);
+                        },
+                        (Some(Action::Submit), _) => page.set_quit(), 
+                        (_, event::KeyCode::Char(ch)) if !ev.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            answer.push(ch);
+                            redraw(&mut stdout, &answer, theme)?;
                         },
-                        event::KeyCode::Enter => page.set_quit(), 
-                        event::KeyCode::Backspace => { 
+                        (_, event::KeyCode::Backspace) => { 
                             answer.pop(); 
-                            execute!(stdout, terminal::Clear(terminal::ClearType::CurrentLine))?;
-                            print!("\r{answer}");
-                            stdout.flush()?;
+                            redraw(&mut stdout, &answer, theme)?;
                         },
                         _ => {}
                     }
@@ -58,7 +72,32 @@ fn read_input() -> Result<String> {
     Ok(answer)
 }
 
-pub fn run(conn: &mut SqliteConnection, value: &Option<String>) -> Result<()> {
+// Asks for a pattern until it has no errors. Stores the canonical spelling
+// so the same pattern is always saved the same. An empty pattern is only
+// accepted when `optional` is set.
+fn read_pattern(config: &GhiConfig, optional: bool) -> Result<Option<String>> {
+    loop {
+        let pattern = read_input(config, true)?;
+        if optional && pattern.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let problems = lint(&pattern);
+        println!();
+        for problem in &problems {
+            println!("{problem}");
+            print_cells(&mut std::io::stdout(), &highlight_input(&pattern, &config.theme), config.color)?;
+            println!("{}", problem.underline());
+        }
+
+        if problems.iter().all(|x| x.level != Level::Error) {
+            return Ok(Some(to_pattern(&CmdParser::compile(&pattern))));
+        }
+        println!("Fix the pattern and enter it again:");
+    }
+}
+
+pub fn run(conn: &mut SqliteConnection, config: &GhiConfig, value: &Option<String>) -> Result<()> {
     let mut src = String::new(); 
 
     if let Some(value) = value {
@@ -75,15 +114,24 @@ pub fn run(conn: &mut SqliteConnection, value: &Option<String>) -> Result<()> {
     println!("Command name: {name}");
     println!("---");
     println!("Transalte {} to pattern: (read docs to see how to do it)", src);
-    let pattern = read_input().unwrap();
+    let pattern = read_pattern(config, false)?.unwrap_or_default();
+
+    println!("\n---");
+    println!("Other patterns that answer it too, one at a time (press enter when done)");
+    let mut alternatives: Vec<String> = Vec::new();
+    while let Some(alternative) = read_pattern(config, true)? {
+        if alternative != pattern && !alternatives.contains(&alternative) {
+            alternatives.push(alternative);
+        }
+    }
 
     println!("\n---");
     println!("Provide the question for the quest");
-    let quest = read_input().unwrap();
+    let quest = read_input(config, false).unwrap();
 
     println!("\n---");
     println!("Any notes about this command? (press enter for none)");
-    let note = read_input().unwrap();
+    let note = read_input(config, false).unwrap();
 
     let lesson_id = get_default_lesson(conn);
 
@@ -96,10 +144,7 @@ pub fn run(conn: &mut SqliteConnection, value: &Option<String>) -> Result<()> {
        lesson_id
     };
 
-    let _res = diesel::insert_into(quests::table)
-        .values(&new_quest)
-        .execute(conn)
-        .expect("Error saving the quest");
+    insert_quest(conn, &new_quest, &alternatives).expect("Error saving the quest");
 
     println!("command \"{}\" added", src);
 
@@ -83,11 +83,7 @@ pub fn run(conn: &mut SqliteConnection, value: &Option<String>) -> Result<()> {
     };
 
     println!("Add context notes for later reference.");
-    let notes = if let Ok(note) = read_input() {
-        Some(note)
-    } else {
-        None
-    };
+    let notes = read_input().ok();
 
     let new_bucket = NewBucket {
         value: &src,
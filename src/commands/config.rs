@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+use std::process::Command;
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use crate::config::{config_files, config_paths, home_dir, Layers};
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the config and where each value comes from
+    Show,
+    /// Print the config files that are read
+    Path,
+    /// Open the config file in $VISUAL or $EDITOR
+    Edit,
+}
+
+/// The values as `key = value  # source` lines, lined up so the sources
+/// read as a column.
+fn render(layers: &Layers, database_dir: &str) -> Vec<String> {
+    let lines: Vec<(String, String)> = layers.settings().into_iter()
+        .map(|(key, value, source)| {
+            let value = if key == "database_dir" { database_dir.to_string() } else { value.to_string() };
+            (format!("{key} = {value}"), source.to_string())
+        })
+        .collect();
+    let width = lines.iter().map(|(line, _)| line.chars().count()).max().unwrap_or_default();
+    lines.into_iter().map(|(line, source)| format!("{line:width$}  # {source}")).collect()
+}
+
+fn edit(path: &PathBuf) -> Result<()> {
+    let editor = std::env::var("VISUAL").ok()
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|x| !x.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Could not create {}", dir.display()))?;
+    }
+
+    // The editor can come with its arguments, like `code --wait`
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Could not start the editor \"{editor}\""))?;
+    if !status.success() {
+        bail!("The editor \"{editor}\" exited with {status}");
+    }
+    Ok(())
+}
+
+pub fn run(flag: &Option<PathBuf>, action: &ConfigAction) -> Result<()> {
+    let home = home_dir()?;
+    match action {
+        ConfigAction::Show => {
+            let layers = Layers::load(flag)?;
+            let config = layers.resolve()?;
+            let database_dir = toml::Value::String(config.database_dir.display().to_string()).to_string();
+            for line in render(&layers, &database_dir) {
+                println!("{line}");
+            }
+        },
+        ConfigAction::Path => {
+            let files = config_files(&home, flag);
+            if files.is_empty() {
+                let path = &config_paths(&home)[0];
+                println!("{}", path.display());
+                eprintln!("There is no config file yet, it can be created at this path");
+            }
+            for file in files {
+                println!("{}", file.display());
+            }
+        },
+        ConfigAction::Edit => {
+            // The file that wins, or a new one where it is looked for first
+            let path = config_files(&home, flag).pop().unwrap_or_else(|| config_paths(&home).swap_remove(0));
+            edit(&path)?;
+            // Tell right away when the edit broke the config
+            Layers::load(flag)?.resolve()?;
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Source;
+
+    #[test]
+    fn render_sources() {
+        let mut layers = Layers::new(PathBuf::from("/home/sam"));
+        layers.merge_str("[theme]\npreset = \"light\"", &Source::File(PathBuf::from("/home/sam/.ghirc"))).unwrap();
+        let lines = render(&layers, "\"/home/sam/.ghi\"");
+
+        assert!(lines.contains(&format!("{:width$}  # default", "database_dir = \"/home/sam/.ghi\"", width = lines[0].find("  #").unwrap())));
+        let preset = lines.iter().find(|x| x.starts_with("theme.preset")).unwrap();
+        assert!(preset.starts_with("theme.preset = \"light\"  "), "{preset}");
+        assert!(preset.ends_with("  # /home/sam/.ghirc"), "{preset}");
+        assert!(lines.iter().all(|x| x.find("  #") == lines[0].find("  #")));
+    }
+}
@@ -1,8 +1,8 @@
 use diesel::SqliteConnection;
 use crossterm::{execute, terminal, QueueableCommand, cursor};
-use crossterm::event::{poll, read, Event, KeyCode, KeyEventKind, KeyModifiers};
-use crossterm::style::Color;
-use crate::window::{ScreenBuf, Cell, Point, Patch, Rect};
+use crossterm::event::{poll, read, Event, KeyEventKind};
+use crate::window::{ScreenBuf, Cell, Point, Patch, Rect, Theme, Action, EXPLORE_ACTIONS, highlight_input};
+use crate::GhiConfig;
 use std::io::Write;
 use crate::db::models;
 use crate::db::lessons::query_all_lessons;
@@ -20,11 +20,12 @@ struct State {
     lessons: Vec<models::Lesson>,
     quests: Vec<models::Quest>,
     quest: Option<models::Quest>,
-    view: View
+    view: View,
+    theme: Theme,
 }
 
 impl State {
-    fn new(conn: &mut SqliteConnection) -> Self {
+    fn new(conn: &mut SqliteConnection, theme: Theme) -> Self {
         let lessons = query_all_lessons(conn);
 
         Self {
@@ -33,7 +34,8 @@ impl State {
             lessons,
             quests: Vec::new(),
             quest: None,
-            view: View::Lessons
+            view: View::Lessons,
+            theme,
         }
     }
 
@@ -116,22 +118,22 @@ impl State {
 
     fn render(&self, page: &mut Screen) {
         let pnt = page.rect.top_left_padded();
-        render_header(&mut page.next_buf, pnt.add(0, 0));
-        render_divider(&mut page.next_buf, pnt.add(0, 3), page.rect.width().into());
+        render_header(&mut page.next_buf, pnt.add(0, 0), &self.theme);
+        render_divider(&mut page.next_buf, pnt.add(0, 3), page.rect.width().into(), &self.theme);
 
         match self.view {
             View::Lessons => {
-                render_lessons(&mut page.next_buf, pnt.add(0, 4), &self.lessons);
+                render_lessons(&mut page.next_buf, pnt.add(0, 4), &self.lessons, &self.theme);
             },
             View::Quests => {
-                render_quests(&mut page.next_buf, pnt.add(0, 4), &self.quests);
+                render_quests(&mut page.next_buf, pnt.add(0, 4), &self.quests, &self.theme);
             },
             View::Quest => {
-                render_quest(&mut page.next_buf, pnt.add(0, 4), &self.quest);
+                render_quest(&mut page.next_buf, pnt.add(0, 4), &self.quest, &self.theme);
             }
         }
 
-        render_selection_caret(&mut page.next_buf, pnt.add(0, 4), self.get_selection_idx()); 
+        render_selection_caret(&mut page.next_buf, pnt.add(0, 4), self.get_selection_idx(), &self.theme);
         let _ = page.stdout.queue(cursor::Hide);
     }
 }
@@ -183,10 +185,10 @@ impl State {
 ///
 ///
 ///
-fn render_header(buf: &mut ScreenBuf, point: Point) {
+fn render_header(buf: &mut ScreenBuf, point: Point, theme: &Theme) {
     let text = "Your available courses"
         .chars()
-        .map(|ch| Cell::new(ch, Color::White))
+        .map(|ch| Cell::new(ch, theme.text))
         .collect();
 
     buf.put_cells(point, text);
@@ -194,26 +196,26 @@ fn render_header(buf: &mut ScreenBuf, point: Point) {
 
 /// Divider
 /// ----------------------------------------------------
-fn render_divider(buf: &mut ScreenBuf, point: Point, screen_width: usize) {
-    let cells = vec![Cell::new('-', Color::White); screen_width];
+fn render_divider(buf: &mut ScreenBuf, point: Point, screen_width: usize, theme: &Theme) {
+    let cells = vec![Cell::new('-', theme.text); screen_width];
     buf.put_cells(point, cells);
 }
 
 /// Selection caret
 /// > 
-fn render_selection_caret(buf: &mut ScreenBuf, point: Point, idx: u16) {
+fn render_selection_caret(buf: &mut ScreenBuf, point: Point, idx: u16, theme: &Theme) {
     let pnt = point.add(0, idx);
-    buf.put_cell(pnt, Cell::new('>', Color::White));
+    buf.put_cell(pnt, Cell::new('>', theme.text));
 }
 
 /// Lessons
 ///   name
 ///   name
 ///   name
-fn render_lessons(buf: &mut ScreenBuf, point: Point, lessons: &Vec<models::Lesson>) {
+fn render_lessons(buf: &mut ScreenBuf, point: Point, lessons: &[models::Lesson], theme: &Theme) {
     for (offset, lesson) in lessons.iter().enumerate() {
         let point = point.add(3, offset as u16);
-        let cells = lesson.cmd.chars().map(|ch| Cell::new(ch, Color::White)).collect();
+        let cells = lesson.cmd.chars().map(|ch| Cell::new(ch, theme.text)).collect();
         buf.put_cells(point, cells);
     }
 }
@@ -222,28 +224,28 @@ fn render_lessons(buf: &mut ScreenBuf, point: Point, lessons: &Vec<models::Lesso
 ///   ..
 ///   name
 ///   name
-fn render_quests(buf: &mut ScreenBuf, point: Point, quests: &Vec<models::Quest>) {
-    let cells = "..".chars().map(|ch| Cell::new(ch, Color::White)).collect();
+fn render_quests(buf: &mut ScreenBuf, point: Point, quests: &[models::Quest], theme: &Theme) {
+    let cells = "..".chars().map(|ch| Cell::new(ch, theme.text)).collect();
     buf.put_cells(point.add(3, 0), cells);
 
     for (offset, quest) in quests.iter().enumerate() {
         let point = point.add(3, (offset + 1) as u16);
-        let cells = quest.pattern.chars().map(|ch| Cell::new(ch, Color::White)).collect();
-        buf.put_cells(point, cells);
+        buf.put_cells(point, highlight_input(&quest.pattern, theme));
     }
 }
 
-fn render_quest(buf: &mut ScreenBuf, point: Point, quest: &Option<models::Quest>) {
-    let cells = "..".chars().map(|ch| Cell::new(ch, Color::White)).collect();
+fn render_quest(buf: &mut ScreenBuf, point: Point, quest: &Option<models::Quest>, theme: &Theme) {
+    let cells = "..".chars().map(|ch| Cell::new(ch, theme.text)).collect();
     buf.put_cells(point.add(3, 0), cells);
 
     if let Some(quest) = quest {
-        let cmd = quest.cmd.chars().map(|ch| Cell::new(ch, Color::White)).collect();
+        let cmd = quest.cmd.chars().map(|ch| Cell::new(ch, theme.text)).collect();
         buf.put_cells(point.add(0, 2), cmd);
+        buf.put_cells(point.add(0, 3), highlight_input(&quest.pattern, theme));
     } else {
         let text = "Not found id"
             .chars()
-            .map(|ch| Cell::new(ch, Color::White))
+            .map(|ch| Cell::new(ch, theme.text))
             .collect();
 
         buf.put_cells(point.add(0, 2), text);
@@ -331,8 +333,8 @@ impl Drop for Screen {
 }
 
 
-pub fn run(conn: &mut SqliteConnection) -> std::io::Result<()> {
-    let mut state = State::new(conn);
+pub fn run(conn: &mut SqliteConnection, config: &GhiConfig) -> std::io::Result<()> {
+    let mut state = State::new(conn, config.theme);
     let mut page = Screen::start()?;
 
     tracing::info!("Start explore command");
@@ -344,21 +346,11 @@ pub fn run(conn: &mut SqliteConnection) -> std::io::Result<()> {
                     page.resize(next_width, next_height)?;
                 },
                 Event::Key(event) if event.kind == KeyEventKind::Press => {
-                    match event.code {
-                        KeyCode::Char(ch) => {
-                            if event.modifiers.contains(KeyModifiers::CONTROL) && ch == 'c' {
-                                page.quit()
-                            } else {
-                                match ch {
-                                    'j' => state.select_down(),
-                                    'k' => state.select_up(),
-                                    _ =>  {}
-                                }
-                            }
-                        },
-                        KeyCode::Up => state.select_up(),
-                        KeyCode::Down => state.select_down(),
-                        KeyCode::Enter => state.select(conn),
+                    match config.keys.action(&event, &EXPLORE_ACTIONS) {
+                        Some(Action::Quit) => page.quit(),
+                        Some(Action::Up) => state.select_up(),
+                        Some(Action::Down) => state.select_down(),
+                        Some(Action::Select) => state.select(conn),
                         _ => {}
                     }
                 },
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use crate::course::{Course, CourseQuest};
+use crate::db::quests::{query_quest_patterns, query_quest_tags, query_quests};
+use crate::db::schema::lessons;
+
+/// Writes a lesson as a course file, to a file or stdout. The file can be
+/// read back with `ghi import`.
+pub fn run(conn: &mut SqliteConnection, lesson: &str, file: &Option<PathBuf>) -> Result<()> {
+    let found: Option<(i32, String, String, String)> = lessons::table
+        .filter(lessons::name.eq(lesson))
+        .select((lessons::id, lessons::name, lessons::cmd, lessons::description))
+        .first(conn)
+        .optional()?;
+    let Some((lesson_id, name, cmd, description)) = found else {
+        bail!("There is no lesson named \"{lesson}\"");
+    };
+
+    let quests = query_quests(conn, lesson_id);
+    // The default lesson is not about one command, so use the one of its quests
+    let command = if cmd.is_empty() {
+        quests.first().map(|x| x.cmd.clone()).unwrap_or_default()
+    } else {
+        cmd
+    };
+
+    let course = Course {
+        name,
+        description,
+        command,
+        quests: quests.iter()
+            .map(|quest| CourseQuest {
+                description: quest.quest.clone(),
+                schema: quest.pattern.clone(),
+                line: 0,
+                alternatives: query_quest_patterns(conn, quest.id).into_iter().map(|x| (x, 0)).collect(),
+                tags: query_quest_tags(conn, quest.id),
+            })
+            .collect(),
+    };
+
+    if let Some(file) = file {
+        std::fs::write(file, course.to_string()).with_context(|| format!("Could not write {}", file.display()))?;
+        println!("Saved {} quests to {}", course.quests.len(), file.display());
+    } else {
+        print!("{course}");
+    }
+
+    Ok(())
+}
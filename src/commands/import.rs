@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use crate::course;
+use crate::db::lessons::insert_lesson;
+use crate::db::models::{NewLesson, NewQuest};
+use crate::db::quests::{insert_quest, insert_quest_tags};
+use crate::parser::parser::{to_pattern, CmdParser};
+
+/// Saves a course file as a new lesson. The patterns are stored in their
+/// canonical spelling, like `ghi add` does.
+pub fn run(conn: &mut SqliteConnection, file: &PathBuf) -> Result<()> {
+    let src = std::fs::read_to_string(file).with_context(|| format!("Could not read {}", file.display()))?;
+    let course = course::parse(&src).with_context(|| format!("Could not parse {}", file.display()))?;
+
+    // Check all the patterns first so a broken file saves nothing
+    let mut quests = Vec::new();
+    for quest in &course.quests {
+        let mut patterns: Vec<String> = Vec::new();
+        let all = std::iter::once((&quest.schema, quest.line)).chain(quest.alternatives.iter().map(|(x, line)| (x, *line)));
+        for (pattern, line) in all {
+            match CmdParser::try_compile(pattern) {
+                Ok(ast) => {
+                    let pattern = to_pattern(&ast);
+                    if !patterns.contains(&pattern) {
+                        patterns.push(pattern);
+                    }
+                },
+                Err(err) => bail!("{}:{line}: {err}", file.display()),
+            }
+        }
+        quests.push((quest, patterns));
+    }
+
+    let name = if course.name.is_empty() { &course.command } else { &course.name };
+    conn.transaction(|conn| {
+        let lesson_id = insert_lesson(conn, &NewLesson {
+            cmd: &course.command,
+            name,
+            description: &course.description,
+            remote: false,
+        })
+        .context("Could not save the lesson")?;
+
+        for (quest, patterns) in &quests {
+            let new_quest = NewQuest {
+                cmd: &course.command,
+                pattern: &patterns[0],
+                quest: &quest.description,
+                is_pattern_literal: false,
+                notes: "",
+                lesson_id,
+            };
+            let quest_id = insert_quest(conn, &new_quest, &patterns[1..]).context("Could not save the quest")?;
+            insert_quest_tags(conn, quest_id, &quest.tags).context("Could not save the quest tags")?;
+        }
+        anyhow::Ok(())
+    })?;
+
+    println!("Saved {} quests to the lesson \"{name}\"", quests.len());
+
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use std::io::{self, Read};
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use crate::db::lessons::insert_lesson;
+use crate::db::models::{NewLesson, NewQuest};
+use crate::db::quests::insert_quest;
+use crate::parser::parser::{to_pattern, CmdParser};
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+// `usage:` in `--help` output, `or:` for the other forms of it.
+fn usage_prefix(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    ["usage:", "or:"].iter()
+        .find(|prefix| trimmed.get(..prefix.len()).is_some_and(|x| x.eq_ignore_ascii_case(prefix)))
+        .map(|prefix| trimmed[prefix.len()..].trim())
+}
+
+/// Finds the usage lines of a `--help` output or of the SYNOPSIS section of
+/// a man page. A usage can wrap over more lines, so the lines indented
+/// deeper than its start are joined to it.
+fn usage_lines(text: &str) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    let mut in_synopsis = false;
+    // The indent of the line the current usage started on
+    let mut current: Option<usize> = None;
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            current = None;
+            continue;
+        }
+
+        if indent(line) == 0 && usage_prefix(line).is_none() {
+            in_synopsis = line.trim() == "SYNOPSIS";
+            current = None;
+            continue;
+        }
+
+        if let Some(usage) = usage_prefix(line) {
+            if !usage.is_empty() {
+                res.push(usage.to_string());
+                current = Some(indent(line));
+            }
+        } else if current.is_some_and(|start| indent(line) > start) {
+            if let Some(last) = res.last_mut() {
+                last.push(' ');
+                last.push_str(line.trim());
+            }
+        } else if in_synopsis {
+            res.push(line.trim().to_string());
+            current = Some(indent(line));
+        }
+    }
+
+    res
+}
+
+// `FILE` and `WHEN` are placeholders in help texts.
+fn is_placeholder(word: &str) -> bool {
+    word.len() > 1
+        && word.starts_with(|c: char| c.is_ascii_uppercase())
+        && word.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+fn is_bare_word(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Rewrites a help usage into our pattern syntax, e.g.
+/// `[-bF] [-t target-pane] FILE...` to `[-b] [-F] [-t <target-pane>] <file>...`
+fn to_pattern_source(usage: &str) -> String {
+    let mut res = Vec::new();
+    let mut after_flag = false;
+
+    for chunk in usage.split_whitespace() {
+        let start = chunk.find(|c| c != '[').unwrap_or(chunk.len());
+        let (prefix, rest) = chunk.split_at(start);
+        let end = rest.trim_end_matches([']', '.']).len();
+        let (mut core, mut suffix) = rest.split_at(end);
+
+        // `--color[=WHEN]` can't have an optional value, `--color=WHEN` can
+        if let Some(idx) = core.find("[=") {
+            core = &core[..idx];
+            suffix = suffix.strip_prefix(']').unwrap_or(suffix);
+        }
+
+        let core = if core.starts_with("--") {
+            match core.split_once('=') {
+                Some((flag, value)) if is_placeholder(value) || is_bare_word(value) => {
+                    format!("{flag}=<{}>", value.to_lowercase())
+                },
+                _ => core.to_string(),
+            }
+        } else if core.starts_with('-') && core.len() > 2 && prefix == "[" && suffix == "]" {
+            // `[-bF]` are the optional flags `[-b] [-F]`
+            res.extend(core[1..].chars().map(|c| format!("[-{c}]")));
+            after_flag = false;
+            continue;
+        } else if is_placeholder(core) || (after_flag && is_bare_word(core)) {
+            format!("<{}>", core.to_lowercase())
+        } else {
+            core.to_string()
+        };
+
+        after_flag = core.starts_with('-') && !core.contains('=') && !suffix.contains(']');
+        res.push(format!("{prefix}{core}{suffix}"));
+    }
+
+    res.join(" ")
+}
+
+/// Reads a `--help` or man page text and saves its usages as a draft
+/// lesson, with placeholder questions to fill in during review.
+pub fn run(conn: &mut SqliteConnection, file: &Option<PathBuf>) -> Result<()> {
+    let text = if let Some(file) = file {
+        std::fs::read_to_string(file).with_context(|| format!("Could not read {}", file.display()))?
+    } else {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf).context("Could not read stdin")?;
+        buf
+    };
+
+    let mut patterns: Vec<String> = Vec::new();
+    for usage in usage_lines(&text) {
+        match CmdParser::try_compile(&to_pattern_source(&usage)) {
+            Ok(ast) => {
+                let pattern = to_pattern(&ast);
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
+            },
+            Err(err) => println!("Skipped `{usage}`: {err}"),
+        }
+    }
+
+    let Some(cmd) = patterns.first().and_then(|x| x.split_whitespace().next()).map(|x| x.to_string()) else {
+        bail!("Could not find any usage lines to compile");
+    };
+
+    let name = format!("{cmd} (draft)");
+    conn.transaction(|conn| {
+        let lesson_id = insert_lesson(conn, &NewLesson {
+            cmd: &cmd,
+            name: &name,
+            description: "Drafted from the help text, review the questions",
+            remote: false,
+        })
+        .context("Could not save the draft lesson")?;
+
+        for pattern in &patterns {
+            let quest = format!("TODO: what does `{pattern}` do?");
+            let new_quest = NewQuest {
+                cmd: &cmd,
+                pattern,
+                quest: &quest,
+                is_pattern_literal: false,
+                notes: "",
+                lesson_id,
+            };
+            insert_quest(conn, &new_quest, &[]).context("Could not save the draft quest")?;
+        }
+        anyhow::Ok(())
+    })?;
+
+    for pattern in &patterns {
+        println!("{pattern}");
+    }
+
+    println!("---");
+    println!("Saved {} quests to the lesson \"{name}\"", patterns.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn help_usage() {
+        let text = "\
+usage: git [-v | --version] [-h | --help] [-C <path>]
+           [--exec-path[=<path>]] <command> [<args>]
+
+These are common Git commands used in various situations:
+   clone     Clone a repository into a new directory
+";
+        assert_eq!(usage_lines(text), vec![
+            "git [-v | --version] [-h | --help] [-C <path>] [--exec-path[=<path>]] <command> [<args>]",
+        ]);
+    }
+
+    #[test]
+    fn help_usage_forms() {
+        let text = "\
+Usage: cp [OPTION]... [-T] SOURCE DEST
+  or:  cp [OPTION]... SOURCE... DIRECTORY
+Copy SOURCE to DEST, or multiple SOURCE(s) to DIRECTORY.
+
+  -a, --archive                same as -dR --preserve=all
+";
+        assert_eq!(usage_lines(text), vec![
+            "cp [OPTION]... [-T] SOURCE DEST",
+            "cp [OPTION]... SOURCE... DIRECTORY",
+        ]);
+    }
+
+    #[test]
+    fn man_synopsis() {
+        let text = "\
+NAME
+     tmux - terminal multiplexer
+
+SYNOPSIS
+     tmux [-2CDlNuVv] [-c shell-command] [-f file]
+          [-L socket-name] [command [flags]]
+
+DESCRIPTION
+     tmux is a terminal multiplexer
+";
+        assert_eq!(usage_lines(text), vec![
+            "tmux [-2CDlNuVv] [-c shell-command] [-f file] [-L socket-name] [command [flags]]",
+        ]);
+    }
+
+    #[test]
+    fn man_non_ascii_name() {
+        let text = "\
+NAME
+     tmux — terminal multiplexer
+
+SYNOPSIS
+     tmux [-2CDlNuVv] [-c shell-command]
+";
+        assert_eq!(usage_lines(text), vec!["tmux [-2CDlNuVv] [-c shell-command]"]);
+    }
+
+    #[test]
+    fn help_to_pattern() {
+        assert_eq!(to_pattern_source("tmux [-bF] [-t target-pane] shell-command"), "tmux [-b] [-F] [-t <target-pane>] shell-command");
+        assert_eq!(to_pattern_source("cp [OPTION]... SOURCE... DIRECTORY"), "cp [<option>]... <source>... <directory>");
+        assert_eq!(to_pattern_source("ls --color[=WHEN] --sort=WORD"), "ls --color --sort=<word>");
+        assert_eq!(to_pattern_source("git [-C <path>] [-v | --version]"), "git [-C <path>] [-v | --version]");
+    }
+
+    #[test]
+    fn help_patterns_compile() {
+        let usages = [
+            "tmux [-bF] [-t target-pane] shell-command",
+            "cp [OPTION]... [-T] SOURCE DEST",
+            "git [-v | --version] [-h | --help] [-C <path>] [--exec-path[=<path>]] <command> [<args>]",
+        ];
+
+        for usage in usages {
+            let res = CmdParser::try_compile(&to_pattern_source(usage));
+            assert!(res.is_ok(), "{usage}: {res:?}");
+        }
+    }
+}
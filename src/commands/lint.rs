@@ -0,0 +1,58 @@
+use std::io::{stdout, IsTerminal};
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use diesel::SqliteConnection;
+use crate::course;
+use crate::db::quests::{query_all_quests, query_quest_patterns};
+use crate::parser::lint::{lint, Problem};
+use crate::window::{highlight_input, print_cells, Cell};
+use crate::GhiConfig;
+
+fn print_problems(config: &GhiConfig, location: &str, pattern: &str, problems: &[Problem]) -> Result<()> {
+    let mut line: Vec<Cell> = "    ".chars().map(|ch| Cell::new(ch, config.theme.text)).collect();
+    line.extend(highlight_input(pattern, &config.theme));
+
+    let mut out = stdout();
+    let color = out.is_terminal() && config.color;
+    for problem in problems {
+        println!("{location}: {problem}");
+        print_cells(&mut out, &line, color)?;
+        println!("    {}", problem.underline());
+    }
+    Ok(())
+}
+
+/// Lints the patterns of a course file or all of the saved quests.
+pub fn run(conn: &mut SqliteConnection, config: &GhiConfig, file: &Option<PathBuf>) -> Result<()> {
+    let mut count = 0;
+
+    if let Some(file) = file {
+        let src = std::fs::read_to_string(file).with_context(|| format!("Could not read {}", file.display()))?;
+        let course = course::parse(&src).with_context(|| format!("Could not parse {}", file.display()))?;
+
+        for quest in course.quests {
+            let patterns = std::iter::once((quest.schema, quest.line)).chain(quest.alternatives);
+            for (pattern, line) in patterns {
+                let problems = lint(&pattern);
+                print_problems(config, &format!("{}:{}", file.display(), line), &pattern, &problems)?;
+                count += problems.len();
+            }
+        }
+    } else {
+        for quest in query_all_quests(conn) {
+            let patterns = std::iter::once(quest.pattern).chain(query_quest_patterns(conn, quest.id));
+            for pattern in patterns {
+                let problems = lint(&pattern);
+                print_problems(config, &format!("quest {} ({})", quest.id, quest.cmd), &pattern, &problems)?;
+                count += problems.len();
+            }
+        }
+    }
+
+    if count > 0 {
+        bail!("Found {count} problems");
+    }
+    println!("No problems found");
+
+    Ok(())
+}
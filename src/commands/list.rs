@@ -0,0 +1,148 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use diesel::SqliteConnection;
+use serde::Serialize;
+use crate::db::lessons::query_lesson_names;
+use crate::db::models::Quest;
+use crate::db::quests::{query_all_quests, query_quest_tags};
+
+/// How `ghi list` and `ghi search` print the quests.
+///
+/// - table: aligned columns with a header, to read
+/// - json: an array of quests, for scripts
+/// - tsv: one quest per line without a header, for `fzf`, `cut` and the like
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+    Tsv,
+}
+
+/// A quest as it is printed, with the name of its lesson and its tags.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct QuestRow {
+    pub id: i32,
+    pub lesson: String,
+    pub pattern: String,
+    pub question: String,
+    pub notes: String,
+    pub tags: Vec<String>,
+}
+
+pub fn quest_rows(conn: &mut SqliteConnection, quests: Vec<Quest>) -> Vec<QuestRow> {
+    let lessons = query_lesson_names(conn);
+    quests.into_iter()
+        .map(|quest| QuestRow {
+            id: quest.id,
+            lesson: lessons.iter().find(|(id, _)| *id == quest.lesson_id).map(|(_, name)| name.clone()).unwrap_or_default(),
+            tags: query_quest_tags(conn, quest.id),
+            pattern: quest.pattern,
+            question: quest.quest,
+            notes: quest.notes.unwrap_or_default(),
+        })
+        .collect()
+}
+
+// Notes can span lines, but a row has to stay on one
+fn one_line(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn cells(row: &QuestRow) -> [String; 6] {
+    [
+        row.id.to_string(),
+        one_line(&row.lesson),
+        one_line(&row.pattern),
+        one_line(&row.question),
+        row.tags.join(","),
+        one_line(&row.notes),
+    ]
+}
+
+fn table(rows: &[QuestRow]) -> String {
+    let header = ["ID", "LESSON", "PATTERN", "QUESTION", "TAGS", "NOTES"].map(|x| x.to_string());
+    let lines: Vec<[String; 6]> = std::iter::once(header).chain(rows.iter().map(cells)).collect();
+
+    let mut widths = [0; 6];
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    lines.iter()
+        .map(|line| {
+            let padded: Vec<String> = line.iter().zip(widths).map(|(cell, width)| format!("{cell:<width$}")).collect();
+            format!("{}\n", padded.join("  ").trim_end())
+        })
+        .collect()
+}
+
+fn tsv(rows: &[QuestRow]) -> String {
+    rows.iter().map(|row| format!("{}\n", cells(row).join("\t"))).collect()
+}
+
+pub fn print(rows: &[QuestRow], format: Format) -> Result<()> {
+    match format {
+        Format::Table if rows.is_empty() => println!("No quests found"),
+        Format::Table => print!("{}", table(rows)),
+        Format::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+        Format::Tsv => print!("{}", tsv(rows)),
+    }
+    Ok(())
+}
+
+/// Prints the saved quests, only the ones of a lesson or with a tag if
+/// they are given.
+pub fn run(conn: &mut SqliteConnection, lesson: &Option<String>, tag: &Option<String>, format: Format) -> Result<()> {
+    let mut quests = query_all_quests(conn);
+
+    if let Some(lesson) = lesson {
+        let lessons = query_lesson_names(conn);
+        let Some((lesson_id, _)) = lessons.iter().find(|(_, name)| name == lesson) else {
+            bail!("There is no lesson named \"{lesson}\"");
+        };
+        quests.retain(|x| x.lesson_id == *lesson_id);
+    }
+
+    let mut rows = quest_rows(conn, quests);
+    if let Some(tag) = tag {
+        rows.retain(|x| x.tags.contains(tag));
+    }
+
+    print(&rows, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i32, pattern: &str, notes: &str) -> QuestRow {
+        QuestRow {
+            id,
+            lesson: "Git".to_string(),
+            pattern: pattern.to_string(),
+            question: "New branch".to_string(),
+            notes: notes.to_string(),
+            tags: vec!["branches".to_string(), "daily".to_string()],
+        }
+    }
+
+    #[test]
+    fn table_columns() {
+        let rows = vec![row(1, "git switch -c <branch>", ""), row(12, "git branch", "")];
+
+        assert_eq!(table(&rows), "\
+ID  LESSON  PATTERN                 QUESTION    TAGS            NOTES
+1   Git     git switch -c <branch>  New branch  branches,daily
+12  Git     git branch              New branch  branches,daily
+");
+    }
+
+    #[test]
+    fn tsv_lines() {
+        let rows = vec![row(1, "git branch", "Lists them\n\twith\tthe current one")];
+
+        assert_eq!(tsv(&rows), "1\tGit\tgit branch\tNew branch\tbranches,daily\tLists them with the current one\n");
+    }
+}
@@ -2,4 +2,12 @@ pub mod run;
 pub mod add;
 pub mod explore;
 pub mod bucket;
-
+pub mod lint;
+pub mod ingest_help;
+pub mod import;
+pub mod export;
+pub mod stats;
+pub mod list;
+pub mod search;
+pub mod show;
+pub mod config;
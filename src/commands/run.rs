@@ -1,23 +1,161 @@
-use std::{fs, thread, mem};
-use std::path::PathBuf;
+use std::{thread, mem};
 use std::io::{self, stdout, Write};
+use diesel::{Connection, SqliteConnection};
+use std::collections::HashMap;
 use crate::parser::parser::{CmdParser, CmdWord};
+use crate::parser::examples::{example_words, to_example, Instance};
 use crate::parser::input_lexer::InputCmdLexer;
-use crate::parser::compare::match_schema;
-use crossterm::{ cursor, style,QueueableCommand};
+use crate::parser::compare::{best_match, match_schema, Grade, Matched, Tolerance};
+use crate::db::quests::{
+    query_all_quests, query_node_stats, query_quest_patterns, query_quests, query_scheduled_quests, record_attempt, record_grade,
+    record_node_grades, record_reverse_grade, record_schedule
+};
+use crate::db::models::{NewAttempt, Quest};
+use crate::schedule::{next_review, Recall};
+use crate::GhiConfig;
+use crossterm::{ cursor, QueueableCommand};
 use crossterm::event::{ KeyCode, KeyModifiers, Event, poll, read, KeyEventKind};
 use crossterm::terminal;
-use anyhow;
-use std::time::Duration;
-use crate::window::{Screen, ScreenBuf, Cell, Rect, apply_patches};
+use anyhow::{self, Context};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::window::{Screen, ScreenBuf, Cell, Point, Rect, Theme, KeyMap, Action, ANSWER_ACTIONS, SUMMARY_ACTIONS, apply_patches, highlight, highlight_words};
 
 
+/// What the user is asked for each quest.
+///
+/// - Forward: the description, answered with the command
+/// - Reverse: the command, answered by picking its description
+/// - Cloze: the command with one word blanked, answered with that word
+#[derive(PartialEq, Clone, Copy)]
+pub enum Mode {
+    Forward,
+    Reverse,
+    Cloze,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Forward => write!(f, "forward"),
+            Mode::Reverse => write!(f, "reverse"),
+            Mode::Cloze => write!(f, "cloze"),
+        }
+    }
+}
+
+/// How a `ghi run` session goes.
+///
+/// - examples: fill the variables with example values
+/// - timed: the seconds there are to answer each quest
+/// - streak: count the right answers in a row and stop at the first miss
+pub struct RunOptions {
+    pub mode: Mode,
+    pub examples: bool,
+    pub timed: Option<u64>,
+    pub streak: bool,
+    pub all: bool,
+}
 
 #[derive(PartialEq)]
 enum View {
     Prompt, 
     Correct,
+    Almost,
     Wrong,
+    Summary,
+}
+
+/// One answer of the round, for the summary at its end.
+///
+/// - missed: the words of the pattern the answer got wrong
+struct Answer {
+    quest_idx: usize,
+    pattern: String,
+    grade: Grade,
+    took: Duration,
+    missed: Vec<String>,
+}
+
+/// How the round went, shown when all of its quests were asked.
+///
+/// - slowest: the patterns that took the longest to answer
+/// - missed: the words missed the most, with how many times
+/// - retry: the quests that were not answered right, to ask again
+#[derive(Debug, PartialEq)]
+struct Summary {
+    correct: usize,
+    total: usize,
+    slowest: Vec<(Duration, String)>,
+    missed: Vec<(String, usize)>,
+    retry: Vec<usize>,
+}
+
+impl Summary {
+    fn of(answers: &[Answer]) -> Self {
+        let mut slowest: Vec<(Duration, String)> = answers.iter().map(|x| (x.took, x.pattern.clone())).collect();
+        slowest.sort_by_key(|x| std::cmp::Reverse(x.0));
+        slowest.truncate(3);
+
+        let mut missed: Vec<(String, usize)> = Vec::new();
+        for word in answers.iter().flat_map(|x| &x.missed) {
+            match missed.iter_mut().find(|(x, _)| x == word) {
+                Some((_, count)) => *count += 1,
+                None => missed.push((word.clone(), 1)),
+            }
+        }
+        // The sort is stable, so the words missed as often stay in the order they came
+        missed.sort_by_key(|x| std::cmp::Reverse(x.1));
+        missed.truncate(5);
+
+        let mut retry = Vec::new();
+        for answer in answers.iter().filter(|x| x.grade != Grade::Correct) {
+            if !retry.contains(&answer.quest_idx) {
+                retry.push(answer.quest_idx);
+            }
+        }
+
+        Self {
+            correct: answers.iter().filter(|x| x.grade == Grade::Correct).count(),
+            total: answers.len(),
+            slowest,
+            missed,
+            retry,
+        }
+    }
+}
+
+/// The quests of the round in the order they are asked and the answers
+/// given so far.
+struct Session {
+    queue: Vec<usize>,
+    pos: usize,
+    answers: Vec<Answer>,
+}
+
+impl Session {
+    fn new(len: usize) -> Self {
+        Self { queue: (0..len).collect(), pos: 0, answers: Vec::new() }
+    }
+
+    fn quest_idx(&self) -> usize {
+        self.queue[self.pos]
+    }
+
+    /// Moves to the next quest, none when the round is over.
+    fn next(&mut self) -> Option<usize> {
+        self.pos += 1;
+        self.queue.get(self.pos).copied()
+    }
+
+    /// Starts a new round with the given quests.
+    fn restart(&mut self, queue: Vec<usize>) {
+        self.queue = queue;
+        self.pos = 0;
+        self.answers.clear();
+    }
 }
 
 struct Prompt {
@@ -25,16 +163,37 @@ struct Prompt {
     title: Vec<char>,
     input: Vec<char>,
     input_cursor: u16,
-    ast: Vec<CmdWord>,
+    // All the accepted patterns of the quest, the main one first
+    asts: Vec<Vec<CmdWord>>,
+    // The pattern the last answer was graded against
+    matched_idx: usize,
+    // The grades of the words of the last answer
+    matched: Vec<Matched>,
+    // The word of the main pattern blanked out in the cloze mode
+    blank: Option<usize>,
+    // When the quest was shown, typed into and answered
+    shown_at: Instant,
+    first_key_at: Option<Instant>,
+    submitted_at: Option<Instant>,
+    // The right answers in a row
+    streak: u32,
+    // How much of the answer is shown as a hint, in words or in letters of the blank
+    hints: usize,
     feedback: Option<Vec<char>>,
+    corrections: Vec<String>,
+    notes: Vec<String>,
+    // The descriptions to pick from in the reverse mode and the right one
+    choices: Vec<String>,
+    answer_idx: usize,
+    error: Option<String>,
     view: View,
     rect: Rect,
+    theme: Theme,
 }
 
 impl Prompt {
-    fn new(title: &str, cmd: &str) -> Self {
-        let cmd_name = cmd.split_whitespace().next().expect("Command must start with a command");
-        let ast = CmdParser::compile(&cmd);
+    fn new(title: &str, asts: Vec<Vec<CmdWord>>) -> Self {
+        let cmd_name = asts[0].first().expect("Command must start with a command").to_string();
         let mut rect = Rect::default();
         rect.set_padding(Some(2), Some(4), Some(1), Some(4));
 
@@ -43,10 +202,24 @@ impl Prompt {
             title: title.chars().collect(),
             input: Vec::new(),
             input_cursor: 0,
-            ast,
+            asts,
+            matched_idx: 0,
+            matched: Vec::new(),
+            blank: None,
+            shown_at: Instant::now(),
+            first_key_at: None,
+            submitted_at: None,
+            streak: 0,
+            hints: 0,
             feedback: None,
+            corrections: Vec::new(),
+            notes: Vec::new(),
+            choices: Vec::new(),
+            answer_idx: 0,
+            error: None,
             view: View::Prompt,
-            rect
+            rect,
+            theme: Theme::default(),
         }
     }
 
@@ -61,39 +234,151 @@ impl Prompt {
         self.rect.set_dimensions(b_w, b_h);
     }
 
-    fn set_question(&mut self, title: &str, cmd: &str) {
-        let cmd_name = cmd.split_whitespace().next().expect("Command must start with a command");
-        let ast = CmdParser::compile(&cmd);
+    fn set_question(&mut self, title: &str, asts: Vec<Vec<CmdWord>>) {
+        let cmd_name = asts[0].first().expect("Command must start with a command").to_string();
         self.cmd =cmd_name.chars().collect();
         self.title = title.chars().collect();
         self.reset_input();
-        self.ast = ast;
+        self.asts = asts;
+        self.matched_idx = 0;
+        self.matched.clear();
+        self.blank = None;
+        self.shown_at = Instant::now();
+        self.first_key_at = None;
+        self.submitted_at = None;
+        self.hints = 0;
         self.feedback = None;
+        self.corrections.clear();
+        self.notes.clear();
+        self.choices.clear();
+        self.error = None;
         self.view = View::Prompt;
     }
 
-    fn submit_answer(&mut self) {
-        let in_lex = InputCmdLexer::compile(&self.get_input_string());
-        let matcher = match_schema(&self.ast, &in_lex, 0, 0);
+    fn set_choices(&mut self, choices: Vec<String>, answer_idx: usize) {
+        self.choices = choices;
+        self.answer_idx = answer_idx;
+    }
+
+    fn set_blank(&mut self, blank: Option<usize>) {
+        self.blank = blank;
+    }
+
+    // The words the answer is graded against, only the blank one in the cloze mode.
+    fn expected(&self) -> &[CmdWord] {
+        match self.blank {
+            Some(idx) => &self.asts[0][idx..=idx],
+            None => &self.asts[self.matched_idx],
+        }
+    }
+
+    fn submit(&mut self, mode: Mode, tolerance: &Tolerance) -> Option<Grade> {
+        let grade = match mode {
+            Mode::Reverse => self.submit_choice(),
+            Mode::Forward | Mode::Cloze => self.submit_answer(tolerance),
+        };
+        if grade.is_some() {
+            self.submitted_at = Some(Instant::now());
+        }
+        grade
+    }
+
+    // The time ran out before a valid answer was submitted.
+    fn time_out(&mut self) -> Grade {
+        self.submitted_at = Some(Instant::now());
+        self.error = None;
+        self.feedback.get_or_insert_with(Vec::new);
+        self.notes = vec!["Out of time".to_string()];
+        self.view = View::Wrong;
+        Grade::Wrong
+    }
+
+    fn hint(&mut self, mode: Mode) {
+        let len = match (mode, self.blank) {
+            (Mode::Reverse, _) => 0,
+            (_, Some(idx)) => to_example(&self.asts[0][idx..=idx]).chars().count(),
+            (_, None) => self.asts[0].len(),
+        };
+        self.hints = (self.hints + 1).min(len);
+    }
+
+    fn time_left(&self, secs: u64) -> Duration {
+        Duration::from_secs(secs).saturating_sub(self.shown_at.elapsed())
+    }
+
+    fn took(&self) -> Duration {
+        self.submitted_at.unwrap_or_else(Instant::now) - self.shown_at
+    }
+
+    fn submit_choice(&mut self) -> Option<Grade> {
+        let picked = match self.get_input_string().trim().parse::<usize>() {
+            Ok(nr) if nr >= 1 && nr <= self.choices.len() => nr - 1,
+            _ => {
+                self.error = Some(format!("Pick one of the options 1-{}", self.choices.len()));
+                return None;
+            }
+        };
+        self.error = None;
 
-        if matcher.iter().all(|x| x.1) {
+        if picked == self.answer_idx {
             self.view = View::Correct;
+            Some(Grade::Correct)
         } else {
-            let mut underline = Vec::new(); 
+            self.view = View::Wrong;
+            Some(Grade::Wrong)
+        }
+    }
+
+    fn submit_answer(&mut self, tolerance: &Tolerance) -> Option<Grade> {
+        let in_lex = match InputCmdLexer::compile(&self.get_input_string()) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                self.error = Some(err.to_string());
+                return None;
+            }
+        };
+        self.error = None;
+        let (matched_idx, matcher) = match self.blank {
+            Some(idx) => (0, match_schema(&self.asts[0][idx..=idx], &in_lex, 0, 0, tolerance)),
+            None => best_match(&self.asts, &in_lex, tolerance),
+        };
+        self.matched_idx = matched_idx;
+        self.matched = matcher.clone();
+        let grade = if in_lex.is_empty() { Grade::Wrong } else { Grade::of(&matcher) };
 
-            for (value, is_match) in matcher {
-                let symbol = if is_match { ' ' } else { '^' };
-                for _ in 0..value.len() {
-                    underline.push(symbol);
+        match grade {
+            Grade::Correct => {
+                self.view = View::Correct;
+            },
+            Grade::Almost => {
+                self.corrections = matcher.iter().filter_map(|x| x.correction()).collect();
+                self.view = View::Almost;
+            },
+            Grade::Wrong => {
+                self.notes = matcher.iter().filter_map(|x| x.note.clone()).collect();
+                let mut underline = Vec::new(); 
+
+                for item in matcher {
+                    let symbol = match item.grade {
+                        Grade::Correct => ' ',
+                        Grade::Almost => '~',
+                        Grade::Wrong => '^',
+                    };
+                    for _ in 0..item.value.len() {
+                        underline.push(symbol);
+                    }
+                    underline.push(' ');
                 }
-                underline.push(' ');
+                self.feedback = Some(underline);
+                self.view = View::Wrong;
             }
-            self.feedback = Some(underline);
-            self.view = View::Wrong;
         }
+
+        Some(grade)
     }
 
     fn append_input(&mut self, ch: char) {
+        self.first_key_at.get_or_insert_with(Instant::now);
         self.input.push(ch);
         self.input_cursor += 1;
     }
@@ -116,68 +401,204 @@ impl Prompt {
 
     fn render_cmd_name(&self, buf: &mut ScreenBuf) {
         let tl = self.rect.top_left_padded(); 
-        let mut text: Vec<Cell> = "Cmd: ".chars().map(|ch| Cell::new(ch,style::Color::White )).collect();
+        let mut text: Vec<Cell> = "Cmd: ".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
         for ch in self.cmd.iter() {
-            text.push(Cell::new(*ch, style::Color::White));
+            text.push(Cell::new(*ch, self.theme.text));
         }
         buf.put_cells(tl, text);
     }
 
+    fn render_status(&self, buf: &mut ScreenBuf, options: &RunOptions) {
+        let mut status = Vec::new();
+        if let (Some(secs), View::Prompt) = (options.timed, &self.view) {
+            status.push(format!("Time: {}s", self.time_left(secs).as_secs_f32().ceil()));
+        }
+        if options.streak {
+            status.push(format!("Streak: {}", self.streak));
+        }
+
+        let tl = self.rect.top_left_padded().add(self.rect.width() / 2, 0);
+        let text = status.join("  ").chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+        buf.put_cells(tl, text);
+    }
+
+    fn render_hint(&self, buf: &mut ScreenBuf) {
+        if self.hints == 0 {
+            return;
+        }
+        let hint = match self.blank {
+            Some(idx) => to_example(&self.asts[0][idx..=idx]).chars().take(self.hints).collect(),
+            None => to_example(&self.asts[0][..self.hints]),
+        };
+        let tl = self.rect.bottom_left_padded();
+        let text = format!("Hint: {hint}").chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+        buf.put_cells(Point::new(tl.x.into(), (tl.y - 2).into()), text);
+    }
+
+    fn render_summary(&self, buf: &mut ScreenBuf, summary: &Summary, keys: &KeyMap) {
+        let mut lines = vec!["Session done".to_string(), String::new()];
+        let percent = summary.correct * 100 / summary.total.max(1);
+        lines.push(format!("Accuracy: {}/{} ({percent}%)", summary.correct, summary.total));
+
+        if !summary.slowest.is_empty() {
+            lines.push(String::new());
+            lines.push("Slowest:".to_string());
+            for (took, pattern) in &summary.slowest {
+                lines.push(format!("  {:>5.1}s  {pattern}", took.as_secs_f32()));
+            }
+        }
+        if !summary.missed.is_empty() {
+            lines.push(String::new());
+            lines.push("Most missed:".to_string());
+            for (word, count) in &summary.missed {
+                lines.push(format!("  {count}x  {word}"));
+            }
+        }
+
+        let tl = self.rect.top_left_padded();
+        for (i, line) in lines.iter().enumerate() {
+            let text = line.chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+            buf.put_cells(tl.add(0, i as u16), text);
+        }
+
+        let submit = keys.label(Action::Submit);
+        let actions = if summary.retry.is_empty() {
+            format!("{submit}: quit")
+        } else {
+            format!("{submit}: retry misses ({})   {}: quit", summary.retry.len(), keys.label(Action::Close))
+        };
+        let text = actions.chars().map(|ch| {
+            Cell::new(ch, self.theme.bar_fg).set_bg(self.theme.bar_bg)
+        }).collect();
+        buf.put_cells(self.rect.bottom_left_padded(), text);
+    }
+
     fn render_question(&self, buf: &mut ScreenBuf) {
         let tl = self.rect.top_left_padded().add(0, 1); 
-        let mut text: Vec<Cell> = "Quest: ".chars().map(|ch| Cell::new(ch, style::Color::White)).collect();
+        let mut text: Vec<Cell> = "Quest: ".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
         for ch in self.title.iter() {
-            text.push(Cell::new(*ch, style::Color::White));
+            text.push(Cell::new(*ch, self.theme.text));
         }
         buf.put_cells(tl, text);
     }
 
     fn render_input(&self, buf: &mut ScreenBuf) {
         let tl = self.rect.bottom_left_padded(); 
-        let text = self.input.iter().map(|ch| Cell::new(*ch, style::Color::White)).collect();
+        let text = self.input.iter().map(|ch| Cell::new(*ch, self.theme.text)).collect();
         buf.put_cells(tl, text);
     }
 
-    fn render_wrong_feedback(&self, buf: &mut ScreenBuf) {
-        let tl = self.rect.top_left_padded().add(0,4);
-        let text = "You missed it:".chars().map(|ch| Cell::new(ch, style::Color::White)).collect();
-        buf.put_cells(tl.clone(), text);
+    fn render_error(&self, buf: &mut ScreenBuf) {
+        if let Some(error) = &self.error {
+            let tl = self.rect.bottom_left_padded().add(0, 0);
+            let text = error.chars().map(|ch| Cell::new(ch, self.theme.error)).collect();
+            buf.put_cells(Point::new(tl.x.into(), (tl.y - 1).into()), text);
+        }
+    }
 
-        let mut offset = 0;
+    fn render_choices(&self, buf: &mut ScreenBuf) {
+        let tl = self.rect.top_left_padded().add(0, 3);
+        for (i, choice) in self.choices.iter().enumerate() {
+            let line = format!("{}) {choice}", i + 1);
+            let text = line.chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+            buf.put_cells(tl.add(2, i as u16), text);
+        }
+    }
 
-        for (_, cmd_word) in self.ast.iter().enumerate() {
-            let word = cmd_word.to_string();
-            for (_, ch) in word.chars().enumerate() {
-                buf.put_cell(tl.add(offset, 1), Cell::new(ch, style::Color::White));
-                offset += 1;
+    fn render_cloze(&self, buf: &mut ScreenBuf) {
+        let Some(blank) = self.blank else {
+            return;
+        };
+        let tl = self.rect.top_left_padded().add(0, 2);
+        let mut text: Vec<Cell> = "Fill in:".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+        let words = highlight_words(&example_words(&self.asts[0]), &self.theme);
+        for (idx, word) in words.into_iter().enumerate() {
+            text.push(Cell::new(' ', self.theme.text));
+            if idx == blank {
+                text.extend(word.iter().map(|_| Cell::new('_', self.theme.syntax)));
+            } else {
+                text.extend(word);
             }
-            buf.put_cell(tl.add(offset, 1), Cell::new(' ', style::Color::White));
-            offset += 1;
         }
+        buf.put_cells(tl, text);
+    }
+
+    fn render_wrong_choice(&self, buf: &mut ScreenBuf) {
+        let tl = self.rect.top_left_padded().add(0, 4);
+        let line = format!("You missed it, it was {}) {}", self.answer_idx + 1, self.choices[self.answer_idx]);
+        let text = line.chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+        buf.put_cells(tl, text);
+    }
+
+    fn render_wrong_feedback(&self, buf: &mut ScreenBuf) {
+        let tl = self.rect.top_left_padded().add(0,4);
+        let text = "You missed it:".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+        buf.put_cells(tl.clone(), text);
+
+        // A blanked word is coloured as it is in the whole pattern
+        let expected = match self.blank {
+            Some(idx) => highlight_words(&self.asts[0], &self.theme).swap_remove(idx),
+            None => highlight(self.expected(), &self.theme),
+        };
+        buf.put_cells(tl.add(0, 1), expected);
 
         if let Some(feedback) = &self.feedback {
             for (i, ch) in feedback.iter().enumerate() {
-                buf.put_cell(tl.add(i as u16, 2), Cell::new(*ch, style::Color::White));
+                buf.put_cell(tl.add(i as u16, 2), Cell::new(*ch, self.theme.text));
             }
         } else {
             panic!("Expected to have feedback, but it was missing");
         }
+
+        for (i, note) in self.notes.iter().enumerate() {
+            let text = note.chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+            buf.put_cells(tl.add(0, (i + 3) as u16), text);
+        }
     }
 
     fn render_correct_feedback(&self, buf: &mut ScreenBuf) {
         let tl = self.rect.top_left_padded().add(0, 4);
-        let text = self.input.iter().map(|ch| Cell::new(*ch, style::Color::White)).collect();
+        let text = self.input.iter().map(|ch| Cell::new(*ch, self.theme.text)).collect();
         buf.put_cells(tl, text);
 
         let tl = self.rect.top_left_padded().add((self.input.len() + 1) as u16, 4); 
-        let text = "Ok!".chars().map(|ch| Cell::new(ch, style::Color::White)).collect();
+        let text = "Ok!".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
         buf.put_cells(tl, text);
+
+        let others: Vec<Vec<Cell>> = self.asts.iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != self.matched_idx)
+            .map(|(_, ast)| highlight(&example_words(ast), &self.theme))
+            .collect();
+        // The reverse mode is about the description, not the patterns
+        if others.is_empty() || !self.choices.is_empty() {
+            return;
+        }
+
+        let tl = self.rect.top_left_padded().add(0, 6);
+        let text = "Also accepted:".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+        buf.put_cells(tl.clone(), text);
+
+        for (i, pattern) in others.into_iter().enumerate() {
+            buf.put_cells(tl.add(2, (i + 1) as u16), pattern);
+        }
     }
 
-    fn render_next_actions(&self, buf: &mut ScreenBuf) {
+    fn render_almost_feedback(&self, buf: &mut ScreenBuf) {
+        let tl = self.rect.top_left_padded().add(0, 4);
+        let text = "Almost! Did you mean:".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+        buf.put_cells(tl.clone(), text);
+
+        for (i, correction) in self.corrections.iter().enumerate() {
+            let text = correction.chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
+            buf.put_cells(tl.add(2, (i + 1) as u16), text);
+        }
+    }
+
+    fn render_next_actions(&self, buf: &mut ScreenBuf, keys: &KeyMap) {
         let tl = self.rect.bottom_left_padded().add(0, 0); 
-        let text = "Next".chars().map(|ch| {
-            Cell::new(ch, style::Color::Black).set_bg(style::Color::White)
+        let text = format!("{}: next", keys.label(Action::Submit)).chars().map(|ch| {
+            Cell::new(ch, self.theme.bar_fg).set_bg(self.theme.bar_bg)
         }).collect();
         buf.put_cells(tl, text);
         
@@ -185,11 +606,11 @@ impl Prompt {
 
     fn render_debug(&self, buf: &mut ScreenBuf, rect: &Rect) {
         let top_left = rect.top_left();
-        let text = "Debug".chars().map(|ch| Cell::new(ch, style::Color::White)).collect();
+        let text = "Debug".chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
         buf.put_cells(top_left.clone(), text);
 
         // for (i, coord) in self.rect.debug().iter().enumerate() {
-        //     let text = coord.chars().map(|ch| Cell::new(ch, style::Color::White)).collect();
+        //     let text = coord.chars().map(|ch| Cell::new(ch, self.theme.text)).collect();
         //     buf.put_cells(top_left.add(0, (1 + i) as u16), text);
         // }
     }
@@ -207,14 +628,14 @@ impl Prompt {
                 } else {
                     ' '
                 };
-                Cell::new(ch, style::Color::White)
+                Cell::new(ch, self.theme.text)
             }).collect();
         buf.put_cells(top_left.clone(), cells.clone());
         buf.put_cells(bottom_left, cells);
 
         for i in 0..=self.rect.height() {
-            buf.put_cell(top_left.add(0, i), Cell::new('-', style::Color::White));
-            buf.put_cell(top_right.add(0, i), Cell::new('-', style::Color::White));
+            buf.put_cell(top_left.add(0, i), Cell::new('-', self.theme.text));
+            buf.put_cell(top_right.add(0, i), Cell::new('-', self.theme.text));
         }
     }
 
@@ -235,21 +656,174 @@ impl Prompt {
 
 
 
-pub fn run() -> anyhow::Result<()>{
-    let data = fs::read_to_string(PathBuf::from("test.txt")).unwrap();
-    let lines: Vec<_> = data.lines().filter(|x| !x.is_empty()).collect();
-    let mut cmds = Vec::new();
-    let mut line_iter = lines.iter();
+// Adds up to three choices in all from `pool` that are not the answer
+// and not in the choices yet.
+fn fill_choices(choices: &mut Vec<String>, answer: &str, pool: impl Iterator<Item = String>, rng: &mut impl Rng) {
+    let mut pool: Vec<String> = pool
+        .filter(|x| x != answer && !choices.contains(x))
+        .collect();
+    pool.sort();
+    pool.dedup();
+    let count = 3usize.saturating_sub(choices.len());
+    choices.extend(pool.choose_multiple(rng, count).cloned());
+}
+
+// The description of the quest shuffled in with a few of the other
+// quests of its lesson. A small lesson is filled up with the quests of
+// the other lessons. Returns the choices and where the right one is.
+fn quest_choices(conn: &mut SqliteConnection, quest: &Quest) -> (Vec<String>, usize) {
+    let mut rng = rand::thread_rng();
+    let mut choices = Vec::new();
+    let lesson = query_quests(conn, quest.lesson_id).into_iter().map(|x| x.quest);
+    fill_choices(&mut choices, &quest.quest, lesson, &mut rng);
+    if choices.len() < 3 {
+        let rest = query_all_quests(conn).into_iter().map(|x| x.quest);
+        fill_choices(&mut choices, &quest.quest, rest, &mut rng);
+    }
 
-    while let Some(line) = line_iter.next() {
-        if line.starts_with('#') {
-            let (_, description) = line.split_once(' ').expect("Title needs to have a value");
-            if let Some(line) = line_iter.next() {
-                cmds.push((description, *line));
-            } else {
-                panic!("didn't find command for a description in the field");
+    choices.push(quest.quest.clone());
+    choices.shuffle(&mut rng);
+    let answer_idx = choices.iter().position(|x| *x == quest.quest).unwrap_or_default();
+
+    (choices, answer_idx)
+}
+
+// How likely a word is to be blanked. Each miss makes it more likely,
+// until it is answered right about as often.
+fn blank_weight(ok_count: i32, miss_count: i32) -> u32 {
+    (1 + 4 * miss_count.max(0) / (1 + ok_count.max(0))) as u32
+}
+
+// The words that can be blanked with their weights. The command names and
+// the connectors are left in place.
+fn blank_weights(words: &[CmdWord], stats: &[(String, i32, i32)]) -> Vec<(usize, u32)> {
+    words.iter()
+        .enumerate()
+        .filter(|(idx, word)| {
+            let is_command = *idx == 0 || matches!(words[idx - 1], CmdWord::Connector(_));
+            !is_command && !matches!(word, CmdWord::Connector(_))
+        })
+        .map(|(idx, word)| {
+            // A flag with a value is graded as one word when it is missing
+            // and as the flag and the value when it is there
+            let node = word.to_string();
+            let (ok, miss) = stats.iter()
+                .filter(|(x, _, _)| *x == node || node.strip_prefix(x.as_str()).is_some_and(|rest| rest.starts_with(' ')))
+                .fold((0, 0), |(ok, miss), (_, x_ok, x_miss)| (ok + x_ok, miss + x_miss));
+            (idx, blank_weight(ok, miss))
+        })
+        .collect()
+}
+
+fn pick_blank(conn: &mut SqliteConnection, quest: &Quest) -> Option<usize> {
+    let words = CmdParser::compile(&quest.pattern);
+    let weights = blank_weights(&words, &query_node_stats(conn, quest.id));
+    let dist = WeightedIndex::new(weights.iter().map(|(_, weight)| *weight)).ok()?;
+    Some(weights[dist.sample(&mut rand::thread_rng())].0)
+}
+
+// The reverse mode shows the command and asks for what it does. With
+// the examples, the variables are filled with values the answer has to use.
+fn set_quest(conn: &mut SqliteConnection, prompt: &mut Prompt, quest: &Quest, mode: Mode, examples: Option<&HashMap<String, Vec<String>>>) {
+    let mut instance = examples.map(Instance::new);
+    let asts: Vec<Vec<CmdWord>> = quest_patterns(conn, quest).iter()
+        .map(|x| {
+            let ast = CmdParser::compile(x);
+            match instance.as_mut() {
+                Some(instance) => instance.fill(&ast),
+                None => ast,
             }
+        })
+        .collect();
+    let title = match instance.filter(|x| !x.values.is_empty()) {
+        Some(instance) => format!("{} ({})", quest.quest, instance.describe()),
+        None => quest.quest.clone(),
+    };
+
+    match mode {
+        Mode::Forward => prompt.set_question(&title, asts),
+        Mode::Reverse => {
+            prompt.set_question(&format!("What does `{}` do?", to_example(&asts[0])), asts);
+            let (choices, answer_idx) = quest_choices(conn, quest);
+            prompt.set_choices(choices, answer_idx);
+        },
+        // A one word command has nothing to blank, so it is asked in full
+        Mode::Cloze => {
+            prompt.set_question(&title, asts);
+            // The examples keep the words in place, so the blank is the same word
+            prompt.set_blank(pick_blank(conn, quest));
+        },
+    }
+}
+
+/// Saves the grades, the schedule and the attempt of an answer in one
+/// transaction, so a failed write does not leave half of them behind.
+/// Only the forward answers, where the whole command is recalled, move the
+/// quest in the schedule. The words are graded by the pattern, so the
+/// answers with example values don't count to them.
+fn record_answer(conn: &mut SqliteConnection, prompt: &Prompt, cmds: &[Quest], quest_idx: usize, options: &RunOptions, grade: Grade) -> anyhow::Result<Answer> {
+    let quest = &cmds[quest_idx];
+    let mode = options.mode;
+    let ms = |x: Duration| x.as_millis().try_into().unwrap_or(i32::MAX);
+
+    conn.transaction(|conn| {
+        if !options.examples && mode != Mode::Reverse {
+            record_node_grades(conn, quest.id, &prompt.matched)?;
         }
+
+        match mode {
+            Mode::Forward => {
+                record_grade(conn, quest.id, grade)?;
+                let recall = Recall::of(grade, prompt.took(), prompt.asts[0].len(), prompt.hints);
+                let (interval, due_at) = next_review(quest.interval_days, recall, chrono::Utc::now().naive_utc());
+                record_schedule(conn, quest.id, interval, due_at)?;
+            },
+            Mode::Reverse => {
+                record_reverse_grade(conn, quest.id, grade)?;
+            },
+            // Only a part of the command was asked, so only the words count
+            Mode::Cloze => {},
+        }
+
+        record_attempt(conn, &NewAttempt {
+            quest_id: quest.id,
+            mode: &mode.to_string(),
+            grade: &grade.to_string(),
+            first_key_ms: prompt.first_key_at.map(|x| ms(x - prompt.shown_at)),
+            submit_ms: ms(prompt.took()),
+            input: &prompt.get_input_string(),
+            hints_used: prompt.hints.try_into().unwrap_or(i32::MAX),
+        })?;
+        diesel::QueryResult::Ok(())
+    }).with_context(|| format!("Could not save the answer to \"{}\"", quest.quest))?;
+
+    Ok(Answer {
+        quest_idx,
+        pattern: quest.pattern.clone(),
+        grade,
+        took: prompt.took(),
+        missed: prompt.matched.iter().filter(|x| x.grade != Grade::Correct).map(|x| x.value.clone()).collect(),
+    })
+}
+
+fn quest_patterns(conn: &mut SqliteConnection, quest: &Quest) -> Vec<String> {
+    let mut res = vec![quest.pattern.clone()];
+    res.extend(query_quest_patterns(conn, quest.id));
+    res
+}
+
+pub fn run(conn: &mut SqliteConnection, config: &GhiConfig, options: &RunOptions) -> anyhow::Result<()>{
+    let due_by = if options.all { None } else { Some(chrono::Utc::now().naive_utc()) };
+    let cmds = query_scheduled_quests(conn, due_by);
+    let mode = options.mode;
+
+    if cmds.is_empty() {
+        if due_by.is_some() && !query_scheduled_quests(conn, None).is_empty() {
+            println!("Nothing is due right now. Come back later, or practice them all with \"ghi run --all\".");
+        } else {
+            println!("There are no quests to practice. Add some with \"ghi add\".");
+        }
+        return Ok(());
     }
 
     let debug = true;
@@ -259,9 +833,13 @@ pub fn run() -> anyhow::Result<()>{
     let (mut term_w, mut term_h) = terminal::size()?;
     let mut curr_buf = ScreenBuf::new(term_w.into(), term_h.into());
     let mut next_buf = ScreenBuf::new(term_w.into(), term_h.into());
-    let mut cmd_idx = 0;
-    let (title, cmd) = cmds.get(cmd_idx).unwrap();
-    let mut prompt = Prompt::new(title, cmd);
+    let mut session = Session::new(cmds.len());
+    let mut summary: Option<Summary> = None;
+    let quest = &cmds[session.quest_idx()];
+    let examples = if options.examples { Some(&config.examples) } else { None };
+    let mut prompt = Prompt::new(&quest.quest, vec![CmdParser::compile(&quest.pattern)]);
+    prompt.theme = config.theme;
+    set_quest(conn, &mut prompt, quest, mode, examples);
     prompt.resize(term_w, term_h);
 
     while !screen.get_quit() {
@@ -276,31 +854,46 @@ pub fn run() -> anyhow::Result<()>{
                     curr_buf.flush(&mut stdout)?;
                 },
                 Event::Key(event) if event.kind == KeyEventKind::Press => {
-                    match event.code {
-                        KeyCode::Char(x) => {
-                            if event.modifiers.contains(KeyModifiers::CONTROL) && x == 'c' {
-                                screen.set_quit();
-                            } else {
-                                prompt.append_input(x);
-                            }
+                    let actions: &[Action] = if prompt.view == View::Summary { &SUMMARY_ACTIONS } else { &ANSWER_ACTIONS };
+                    match (config.keys.action(&event, actions), event.code) {
+                        (Some(Action::Quit | Action::Close), _) => {
+                            screen.set_quit();
                         },
-                        KeyCode::Backspace => {
-                            prompt.backspace_input();
+                        (Some(Action::Hint), _) if prompt.view == View::Prompt => {
+                            prompt.hint(mode);
                         },
-                        KeyCode::Enter => {
+                        (Some(Action::Submit), _) => {
                             if prompt.view == View::Prompt {
-                                prompt.submit_answer();
-                            } else {
-                                cmd_idx += 1;
-                                if cmd_idx > cmds.len() {
+                                if let Some(grade) = prompt.submit(mode, &config.tolerance) {
+                                    let answer = record_answer(conn, &prompt, &cmds, session.quest_idx(), options, grade)?;
+                                    session.answers.push(answer);
+                                    prompt.streak = if grade == Grade::Correct { prompt.streak + 1 } else { 0 };
+                                }
+                            } else if let Some(done) = summary.take() {
+                                if done.retry.is_empty() {
                                     screen.set_quit();
                                 } else {
-                                    let (t, c) = cmds.get(cmd_idx).unwrap();
-                                    prompt.set_question(t, c);
-                                    prompt.reset_input();
+                                    session.restart(done.retry);
+                                    set_quest(conn, &mut prompt, &cmds[session.quest_idx()], mode, examples);
+                                }
+                            } else {
+                                // A streak is over at the first miss
+                                let streak_over = options.streak && prompt.view != View::Correct;
+                                match session.next().filter(|_| !streak_over) {
+                                    Some(idx) => set_quest(conn, &mut prompt, &cmds[idx], mode, examples),
+                                    None => {
+                                        summary = Some(Summary::of(&session.answers));
+                                        prompt.view = View::Summary;
+                                    },
                                 }
                             }
                         },
+                        (_, KeyCode::Char(x)) if prompt.view != View::Summary && !event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            prompt.append_input(x);
+                        },
+                        (_, KeyCode::Backspace) => {
+                            prompt.backspace_input();
+                        },
                         _ => {}
                     }
                 },
@@ -308,22 +901,56 @@ pub fn run() -> anyhow::Result<()>{
             }
         }
 
+        if let Some(secs) = options.timed {
+            if prompt.view == View::Prompt && prompt.time_left(secs).is_zero() {
+                let grade = prompt.submit(mode, &config.tolerance)
+                    .filter(|_| !prompt.input.is_empty())
+                    .unwrap_or_else(|| prompt.time_out());
+                let answer = record_answer(conn, &prompt, &cmds, session.quest_idx(), options, grade)?;
+                session.answers.push(answer);
+                prompt.streak = if grade == Grade::Correct { prompt.streak + 1 } else { 0 };
+            }
+        }
+
         next_buf.clear();
-        prompt.render_cmd_name(&mut next_buf);
         prompt.render_boundary(&mut next_buf);
-        prompt.render_question(&mut next_buf);
+        if prompt.view != View::Summary {
+            prompt.render_cmd_name(&mut next_buf);
+            prompt.render_status(&mut next_buf, options);
+            prompt.render_question(&mut next_buf);
+        }
 
         match prompt.view {
+            View::Summary => {
+                if let Some(summary) = &summary {
+                    prompt.render_summary(&mut next_buf, summary, &config.keys);
+                }
+            },
             View::Prompt => {
+                match mode {
+                    Mode::Reverse => prompt.render_choices(&mut next_buf),
+                    Mode::Cloze => prompt.render_cloze(&mut next_buf),
+                    Mode::Forward => {},
+                }
+                prompt.render_hint(&mut next_buf);
+                prompt.render_error(&mut next_buf);
                 prompt.render_input(&mut next_buf);
             },
             View::Correct => {
                 prompt.render_correct_feedback(&mut next_buf);
-                prompt.render_next_actions(&mut next_buf);
+                prompt.render_next_actions(&mut next_buf, &config.keys);
+            },
+            View::Almost => {
+                prompt.render_almost_feedback(&mut next_buf);
+                prompt.render_next_actions(&mut next_buf, &config.keys);
+            },
+            View::Wrong if mode == Mode::Reverse => {
+                prompt.render_wrong_choice(&mut next_buf);
+                prompt.render_next_actions(&mut next_buf, &config.keys);
             },
             View::Wrong => {
                 prompt.render_wrong_feedback(&mut next_buf);
-                prompt.render_next_actions(&mut next_buf);
+                prompt.render_next_actions(&mut next_buf, &config.keys);
             },
         }
 
@@ -343,3 +970,73 @@ pub fn run() -> anyhow::Result<()>{
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cloze_blank_weights() {
+        let words = CmdParser::compile("git commit -m <message> && git push");
+        let stats = vec![("-m <message>".to_string(), 1, 3), ("push".to_string(), 5, 0), ("-m".to_string(), 0, 1)];
+
+        assert_eq!(blank_weights(&words, &stats), vec![(1, 1), (2, 9), (5, 1)]);
+        assert!(blank_weights(&CmdParser::compile("ls"), &[]).is_empty());
+    }
+
+    #[test]
+    fn reverse_choices_are_filled_up() {
+        let mut rng = rand::thread_rng();
+        let mut choices = Vec::new();
+        let lesson = ["Stash changes", "Stash changes", "List stashes"].map(String::from);
+        fill_choices(&mut choices, "Stash changes", lesson.into_iter(), &mut rng);
+        assert_eq!(choices, vec!["List stashes"]);
+
+        let rest = ["List stashes", "Stash changes", "Switch branch", "Amend a commit", "Push"].map(String::from);
+        fill_choices(&mut choices, "Stash changes", rest.into_iter(), &mut rng);
+        assert_eq!(choices.len(), 3);
+        assert_eq!(choices[0], "List stashes");
+        assert!(!choices[1..].iter().any(|x| x == "Stash changes" || x == "List stashes"));
+    }
+
+    fn answer(quest_idx: usize, pattern: &str, grade: Grade, secs: u64, missed: &[&str]) -> Answer {
+        Answer {
+            quest_idx,
+            pattern: pattern.to_string(),
+            grade,
+            took: Duration::from_secs(secs),
+            missed: missed.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn session_summary() {
+        let answers = vec![
+            answer(0, "git stash list", Grade::Correct, 2, &[]),
+            answer(1, "git rebase -i <commit>", Grade::Wrong, 9, &["-i", "<commit>"]),
+            answer(2, "git commit --amend", Grade::Almost, 4, &["--amend"]),
+            answer(1, "git rebase -i <commit>", Grade::Wrong, 7, &["-i"]),
+        ];
+        let summary = Summary::of(&answers);
+
+        assert_eq!(summary.correct, 1);
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.slowest[0], (Duration::from_secs(9), "git rebase -i <commit>".to_string()));
+        assert_eq!(summary.slowest.len(), 3);
+        assert_eq!(summary.missed, vec![("-i".to_string(), 2), ("<commit>".to_string(), 1), ("--amend".to_string(), 1)]);
+        assert_eq!(summary.retry, vec![1, 2]);
+    }
+
+    #[test]
+    fn session_rounds() {
+        let mut session = Session::new(3);
+        assert_eq!(session.quest_idx(), 0);
+        assert_eq!(session.next(), Some(1));
+        assert_eq!(session.next(), Some(2));
+        assert_eq!(session.next(), None);
+
+        session.restart(vec![2]);
+        assert_eq!(session.quest_idx(), 2);
+        assert_eq!(session.next(), None);
+    }
+}
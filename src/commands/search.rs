@@ -0,0 +1,70 @@
+use anyhow::Result;
+use diesel::SqliteConnection;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use crate::commands::list::{print, quest_rows, Format, QuestRow};
+use crate::db::quests::query_all_quests;
+
+// The best score of the fields, none if the query is in none of them
+fn score(matcher: &SkimMatcherV2, row: &QuestRow, query: &str) -> Option<i64> {
+    [&row.pattern, &row.question, &row.notes].iter()
+        .filter_map(|x| matcher.fuzzy_match(x, query))
+        .max()
+}
+
+/// The rows the query fuzzy matches, the best match first.
+fn rank(rows: Vec<QuestRow>, query: &str) -> Vec<QuestRow> {
+    let matcher = SkimMatcherV2::default();
+    let mut found: Vec<(i64, QuestRow)> = rows.into_iter()
+        .filter_map(|row| score(&matcher, &row, query).map(|x| (x, row)))
+        .collect();
+    found.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(a.id.cmp(&b.id)));
+    found.into_iter().map(|(_, row)| row).collect()
+}
+
+/// Prints the quests whose pattern, question or notes match the query.
+pub fn run(conn: &mut SqliteConnection, query: &str, format: Format) -> Result<()> {
+    let quests = query_all_quests(conn);
+    let rows = quest_rows(conn, quests);
+    print(&rank(rows, query), format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: i32, pattern: &str, question: &str, notes: &str) -> QuestRow {
+        QuestRow {
+            id,
+            lesson: "Git".to_string(),
+            pattern: pattern.to_string(),
+            question: question.to_string(),
+            notes: notes.to_string(),
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn rank_matches() {
+        let rows = vec![
+            row(1, "git stash list", "Show the stashes", ""),
+            row(2, "git switch -c <branch>", "Create a branch and switch to it", ""),
+            row(3, "git log --oneline", "Short history", "One commit per line, good to find a branch"),
+            row(4, "git commit --amend", "Change the last commit", ""),
+        ];
+
+        let ids: Vec<i32> = rank(rows, "swtch").iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec![2]);
+    }
+
+    #[test]
+    fn search_notes() {
+        let rows = vec![
+            row(1, "git stash list", "Show the stashes", ""),
+            row(2, "git log --oneline", "Short history", "One commit per line"),
+        ];
+
+        let ids: Vec<i32> = rank(rows, "per line").iter().map(|x| x.id).collect();
+        assert_eq!(ids, vec![2]);
+    }
+}
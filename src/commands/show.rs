@@ -0,0 +1,85 @@
+use std::io::{stdout, IsTerminal, Write};
+use anyhow::{bail, Result};
+use crossterm::style::Color;
+use diesel::SqliteConnection;
+use crate::db::lessons::query_lesson_names;
+use crate::db::models::Quest;
+use crate::db::quests::{query_all_quests, query_quest_patterns};
+use crate::parser::parser::CmdParser;
+use crate::window::{highlight, print_cells, Cell, Theme};
+use crate::GhiConfig;
+
+fn text(value: &str, color: Color) -> Vec<Cell> {
+    value.chars().map(|ch| Cell::new(ch, color)).collect()
+}
+
+fn indented(indent: &str, mut cells: Vec<Cell>, theme: &Theme) -> Vec<Cell> {
+    let mut line = text(indent, theme.text);
+    line.append(&mut cells);
+    line
+}
+
+/// A quest with all the patterns that answer it.
+struct Entry<'a> {
+    quest: &'a Quest,
+    patterns: Vec<String>,
+}
+
+/// The cheat sheet of a command, like `tldr` prints it. The quests are
+/// grouped by lesson, each with its patterns and notes.
+fn render(cmd: &str, lessons: &[(String, Vec<Entry>)], theme: &Theme) -> Vec<Vec<Cell>> {
+    let mut lines = vec![vec![], text(&format!("  {cmd}"), theme.command)];
+
+    for (name, entries) in lessons {
+        lines.push(vec![]);
+        lines.push(text(&format!("  {name}"), theme.muted));
+
+        for Entry { quest, patterns } in entries {
+            lines.push(vec![]);
+            lines.push(text(&format!("  - {}:", quest.quest), theme.text));
+            lines.push(vec![]);
+            for pattern in patterns {
+                lines.push(indented("    ", highlight(&CmdParser::compile(pattern), theme), theme));
+            }
+            let notes = quest.notes.as_deref().unwrap_or_default();
+            for line in notes.lines().filter(|x| !x.trim().is_empty()) {
+                lines.push(text(&format!("    {}", line.trim()), theme.muted));
+            }
+        }
+    }
+    lines.push(vec![]);
+
+    lines
+}
+
+/// Prints the saved quests of a command as a quick reference. The colours
+/// are left out when the output is not a terminal, e.g. piped to a file.
+pub fn run(conn: &mut SqliteConnection, config: &GhiConfig, cmd: &str) -> Result<()> {
+    let quests: Vec<Quest> = query_all_quests(conn).into_iter().filter(|x| x.cmd == cmd).collect();
+    if quests.is_empty() {
+        bail!("There are no quests for \"{cmd}\"");
+    }
+
+    let mut lessons = Vec::new();
+    for (lesson_id, name) in query_lesson_names(conn) {
+        let lesson: Vec<Entry> = quests.iter()
+            .filter(|x| x.lesson_id == lesson_id)
+            .map(|quest| Entry {
+                quest,
+                patterns: std::iter::once(quest.pattern.clone()).chain(query_quest_patterns(conn, quest.id)).collect(),
+            })
+            .collect();
+        if !lesson.is_empty() {
+            lessons.push((name, lesson));
+        }
+    }
+
+    let mut out = stdout();
+    let color = out.is_terminal() && config.color;
+    for line in render(cmd, &lessons, &config.theme) {
+        print_cells(&mut out, &line, color)?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use std::io::{stdout, IsTerminal};
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
+use crossterm::style::Color;
+use diesel::SqliteConnection;
+use serde::Serialize;
+use crate::db::lessons::query_lesson_names;
+use crate::db::models::Quest;
+use crate::db::quests::{query_all_quests, query_attempt_history};
+use crate::window::{Cell, Point, ScreenBuf, Theme};
+use crate::GhiConfig;
+
+// A quest asked again only after this many days is known well.
+const MASTERED_DAYS: i32 = 4;
+// The days of the accuracy heatmap, up to today.
+const HISTORY_DAYS: i64 = 14;
+const BAR_WIDTH: usize = 10;
+
+#[derive(Serialize, Debug, PartialEq)]
+struct LessonStats {
+    name: String,
+    quests: usize,
+    mastered: usize,
+    accuracy: Option<f32>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct QuestStats {
+    id: i32,
+    pattern: String,
+    quest: String,
+    ok_count: i32,
+    display_count: i32,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Streak {
+    current: usize,
+    longest: usize,
+}
+
+/// The answers of one day. A day without answers has no accuracy.
+#[derive(Serialize, Debug, PartialEq)]
+struct DayStats {
+    date: NaiveDate,
+    correct: usize,
+    total: usize,
+}
+
+#[derive(Serialize)]
+struct Report {
+    lessons: Vec<LessonStats>,
+    due_today: Vec<QuestStats>,
+    streak: Streak,
+    daily: Vec<DayStats>,
+    weakest: Vec<QuestStats>,
+}
+
+fn ratio(ok: i32, total: i32) -> Option<f32> {
+    if total > 0 { Some(ok as f32 / total as f32) } else { None }
+}
+
+fn quest_stats(quest: &Quest) -> QuestStats {
+    QuestStats {
+        id: quest.id,
+        pattern: quest.pattern.clone(),
+        quest: quest.quest.clone(),
+        ok_count: quest.ok_count,
+        display_count: quest.display_count,
+    }
+}
+
+fn lesson_stats(lessons: &[(i32, String)], quests: &[Quest]) -> Vec<LessonStats> {
+    lessons.iter()
+        .map(|(id, name)| {
+            let quests: Vec<&Quest> = quests.iter().filter(|x| x.lesson_id == *id).collect();
+            LessonStats {
+                name: name.clone(),
+                quests: quests.len(),
+                mastered: quests.iter().filter(|x| x.interval_days >= MASTERED_DAYS).count(),
+                accuracy: ratio(quests.iter().map(|x| x.ok_count).sum(), quests.iter().map(|x| x.display_count).sum()),
+            }
+        })
+        .filter(|x| x.quests > 0)
+        .collect()
+}
+
+/// The days in a row with answers, ending today or yesterday so the
+/// streak is not lost before today's practice.
+fn streak(days: &[NaiveDate], today: NaiveDate) -> Streak {
+    let mut days = days.to_vec();
+    days.sort();
+    days.dedup();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for day in &days {
+        run = if prev.is_some_and(|x| x.succ_opt() == Some(*day)) { run + 1 } else { 1 };
+        longest = longest.max(run);
+        prev = Some(*day);
+    }
+
+    let yesterday = today.pred_opt().unwrap_or(today);
+    let current = if prev.is_some_and(|x| x == today || x == yesterday) { run } else { 0 };
+
+    Streak { current, longest }
+}
+
+fn daily_stats(attempts: &[(NaiveDate, bool)], today: NaiveDate) -> Vec<DayStats> {
+    (0..HISTORY_DAYS).rev()
+        .map(|ago| today - chrono::Duration::days(ago))
+        .map(|date| {
+            let day: Vec<bool> = attempts.iter().filter(|(x, _)| *x == date).map(|(_, ok)| *ok).collect();
+            DayStats { date, correct: day.iter().filter(|x| **x).count(), total: day.len() }
+        })
+        .collect()
+}
+
+// The quests answered right the least, of the ones that were asked.
+fn weakest(quests: &[Quest]) -> Vec<QuestStats> {
+    let mut asked: Vec<&Quest> = quests.iter().filter(|x| x.display_count > 0).collect();
+    asked.sort_by(|a, b| {
+        let a_ratio = ratio(a.ok_count, a.display_count).unwrap_or_default();
+        let b_ratio = ratio(b.ok_count, b.display_count).unwrap_or_default();
+        a_ratio.total_cmp(&b_ratio).then(b.miss_count.cmp(&a.miss_count))
+    });
+    asked.iter().filter(|x| x.ok_count < x.display_count).take(5).map(|x| quest_stats(x)).collect()
+}
+
+fn is_due(quest: &Quest, today: NaiveDate) -> bool {
+    quest.due_at.is_none_or(|x| x.date() <= today)
+}
+
+fn text(value: &str, color: Color) -> Vec<Cell> {
+    value.chars().map(|ch| Cell::new(ch, color)).collect()
+}
+
+fn accuracy_color(accuracy: f32, theme: &Theme) -> Color {
+    if accuracy >= 0.8 {
+        theme.good
+    } else if accuracy >= 0.5 {
+        theme.warn
+    } else {
+        theme.error
+    }
+}
+
+fn bar(accuracy: Option<f32>, theme: &Theme) -> Vec<Cell> {
+    let accuracy = accuracy.unwrap_or_default();
+    let filled = (accuracy * BAR_WIDTH as f32).round() as usize;
+    (0..BAR_WIDTH)
+        .map(|idx| if idx < filled { Cell::new('█', accuracy_color(accuracy, theme)) } else { Cell::new('░', theme.muted) })
+        .collect()
+}
+
+fn percent(accuracy: Option<f32>) -> String {
+    accuracy.map_or("-".to_string(), |x| format!("{:.0}%", x * 100.0))
+}
+
+// The report as lines of cells, to color the bars and the heatmap.
+fn render(report: &Report, theme: &Theme) -> Vec<Vec<Cell>> {
+    let mut lines: Vec<Vec<Cell>> = Vec::new();
+    let name_width = report.lessons.iter().map(|x| x.name.chars().count()).max().unwrap_or_default();
+
+    lines.push(text("Lessons", theme.text));
+    for lesson in &report.lessons {
+        let mut line = text(&format!("  {:<name_width$}  {:>3} quests  {:>3} mastered  ", lesson.name, lesson.quests, lesson.mastered), theme.text);
+        line.extend(bar(lesson.accuracy, theme));
+        line.extend(text(&format!(" {}", percent(lesson.accuracy)), theme.text));
+        lines.push(line);
+    }
+
+    lines.push(vec![]);
+    lines.push(text(&format!("Due today: {}", report.due_today.len()), theme.text));
+    for quest in report.due_today.iter().take(5) {
+        lines.push(text(&format!("  {}  {}", quest.pattern, quest.quest), theme.text));
+    }
+    if report.due_today.len() > 5 {
+        lines.push(text(&format!("  and {} more", report.due_today.len() - 5), theme.text));
+    }
+
+    lines.push(vec![]);
+    let days = |x: usize| if x == 1 { "day" } else { "days" };
+    let streak = &report.streak;
+    lines.push(text(&format!("Streak: {} {} (longest {} {})", streak.current, days(streak.current), streak.longest, days(streak.longest)), theme.text));
+
+    lines.push(vec![]);
+    lines.push(text(&format!("Accuracy, last {HISTORY_DAYS} days"), theme.text));
+    let mut heatmap = text("  ", theme.text);
+    for day in &report.daily {
+        heatmap.push(match ratio(day.correct as i32, day.total as i32) {
+            Some(accuracy) => Cell::new('■', accuracy_color(accuracy, theme)),
+            None => Cell::new('·', theme.muted),
+        });
+        heatmap.push(Cell::new(' ', theme.text));
+    }
+    let (correct, total) = report.daily.iter().fold((0, 0), |(c, t), x| (c + x.correct, t + x.total));
+    heatmap.extend(text(&format!(" {}", percent(ratio(correct as i32, total as i32))), theme.text));
+    lines.push(heatmap);
+    if let (Some(first), Some(last)) = (report.daily.first(), report.daily.last()) {
+        lines.push(text(&format!("  {} to {}", first.date.format("%m-%d"), last.date.format("%m-%d")), theme.muted));
+    }
+
+    if !report.weakest.is_empty() {
+        lines.push(vec![]);
+        lines.push(text("Weakest", theme.text));
+        for quest in &report.weakest {
+            let accuracy = ratio(quest.ok_count, quest.display_count);
+            let mut line = text("  ", theme.text);
+            line.extend(text(&format!("{:>4}", percent(accuracy)), accuracy_color(accuracy.unwrap_or_default(), theme)));
+            line.extend(text(&format!("  {}  ({}/{})", quest.pattern, quest.ok_count, quest.display_count), theme.text));
+            lines.push(line);
+        }
+    }
+
+    lines
+}
+
+/// Prints how the practice is going, as text or as `--json`.
+pub fn run(conn: &mut SqliteConnection, config: &GhiConfig, json: bool) -> Result<()> {
+    let today = chrono::Utc::now().date_naive();
+    let quests = query_all_quests(conn);
+    let attempts: Vec<(NaiveDate, bool)> = query_attempt_history(conn).into_iter()
+        .map(|(_, grade, created_at): (i32, String, NaiveDateTime)| (created_at.date(), grade == "correct"))
+        .collect();
+    let days: Vec<NaiveDate> = attempts.iter().map(|(x, _)| *x).collect();
+
+    let report = Report {
+        lessons: lesson_stats(&query_lesson_names(conn), &quests),
+        due_today: quests.iter().filter(|x| is_due(x, today)).map(quest_stats).collect(),
+        streak: streak(&days, today),
+        daily: daily_stats(&attempts, today),
+        weakest: weakest(&quests),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let lines = render(&report, &config.theme);
+    let width = lines.iter().map(|x| x.len()).max().unwrap_or_default().max(1);
+    let mut buf = ScreenBuf::new(width.try_into()?, lines.len().try_into()?);
+    for (y, line) in lines.into_iter().enumerate() {
+        buf.put_cells(Point::new(0, y), line);
+    }
+
+    let mut out = stdout();
+    let color = out.is_terminal() && config.color;
+    buf.print(&mut out, color)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    #[test]
+    fn streaks() {
+        let days = vec![date(1), date(2), date(3), date(3), date(10), date(11)];
+
+        assert_eq!(streak(&days, date(11)), Streak { current: 2, longest: 3 });
+        assert_eq!(streak(&days, date(12)), Streak { current: 2, longest: 3 });
+        assert_eq!(streak(&days, date(13)), Streak { current: 0, longest: 3 });
+        assert_eq!(streak(&[], date(13)), Streak { current: 0, longest: 0 });
+    }
+
+    #[test]
+    fn daily_accuracy() {
+        let attempts = vec![(date(18), true), (date(18), false), (date(19), true), (date(1), true)];
+        let daily = daily_stats(&attempts, date(19));
+
+        assert_eq!(daily.len(), 14);
+        assert_eq!(daily[0].date, date(6));
+        assert_eq!(daily[12], DayStats { date: date(18), correct: 1, total: 2 });
+        assert_eq!(daily[13], DayStats { date: date(19), correct: 1, total: 1 });
+        assert_eq!(daily[11].total, 0);
+    }
+
+    #[test]
+    fn accuracy_bars() {
+        let theme = Theme::default();
+        let cells = bar(Some(0.3), &theme);
+        assert_eq!(cells.iter().filter(|x| x.ch == '█').count(), 3);
+        assert_eq!(cells[0].fg, theme.error);
+        assert_eq!(percent(Some(0.3)), "30%");
+        assert_eq!(percent(None), "-");
+    }
+}
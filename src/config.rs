@@ -0,0 +1,481 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use toml::{Table, Value};
+use crate::parser::compare::Tolerance;
+use crate::window::{self, KeyMap, Theme};
+
+pub struct GhiConfig {
+    // - $HOME/.ghi, for the logs
+    pub config_dir: PathBuf,
+    // `database_dir` in the config, $HOME/.ghi by default
+    pub database_dir: PathBuf,
+    // [grading] in the config
+    pub tolerance: Tolerance,
+    // [examples] in the config, the values of `ghi run --examples` by variable name
+    pub examples: HashMap<String, Vec<String>>,
+    // [theme] in the config, or no colours with `NO_COLOR` and no [theme]
+    pub theme: Theme,
+    // Whether to colour what is printed to a terminal
+    pub color: bool,
+    // [keys] in the config
+    pub keys: KeyMap,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GhiUserConfig {
+   database_dir: Option<String>,
+   grading: Option<GhiGradingConfig>,
+   examples: Option<HashMap<String, Vec<String>>>,
+   theme: Option<HashMap<String, String>>,
+   keys: Option<HashMap<String, GhiKeysConfig>>,
+}
+
+// A key or a list of keys for an action
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GhiKeysConfig {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GhiGradingConfig {
+    strict: Option<bool>,
+    typo_distance: Option<usize>,
+    short_alias: Option<bool>,
+}
+
+fn parse_tolerance(value: &Option<GhiGradingConfig>) -> Tolerance {
+    let mut tolerance = Tolerance::default();
+    if let Some(grading) = value {
+        if grading.strict == Some(true) {
+            return Tolerance::strict();
+        }
+        if let Some(distance) = grading.typo_distance {
+            tolerance.distance = distance;
+        }
+        if let Some(short_alias) = grading.short_alias {
+            tolerance.short_alias = short_alias;
+        }
+    }
+    tolerance
+}
+
+fn parse_keys(value: &Option<HashMap<String, GhiKeysConfig>>) -> Result<KeyMap, String> {
+    let Some(keys) = value else {
+        return Ok(KeyMap::default());
+    };
+    let keys = keys.iter()
+        .map(|(action, keys)| {
+            let keys = match keys {
+                GhiKeysConfig::One(key) => vec![key.clone()],
+                GhiKeysConfig::Many(keys) => keys.clone(),
+            };
+            (action.clone(), keys)
+        })
+        .collect();
+    KeyMap::parse(&keys)
+}
+
+/// Where a value of the config comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(String),
+    NoColor,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(name) => write!(f, "${name}"),
+            Source::NoColor => write!(f, "$NO_COLOR"),
+        }
+    }
+}
+
+/// The config files in the order they are looked for, the first one that
+/// exists is read.
+///
+/// - $XDG_CONFIG_HOME/ghi/config.toml, or $HOME/.config/ghi/config.toml
+/// - $HOME/.ghi/config.toml
+/// - $HOME/.ghirc
+pub fn config_paths(home: &Path) -> Vec<PathBuf> {
+    // Relative paths in XDG_CONFIG_HOME are invalid and should be ignored
+    let xdg = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .unwrap_or_else(|| home.join(".config"));
+    vec![xdg.join("ghi/config.toml"), home.join(".ghi/config.toml"), home.join(".ghirc")]
+}
+
+/// The config files that are read, the one of `--config` last as it wins.
+pub fn config_files(home: &Path, flag: &Option<PathBuf>) -> Vec<PathBuf> {
+    config_paths(home).into_iter()
+        .find(|x| x.exists())
+        .into_iter()
+        .chain(flag.clone())
+        .collect()
+}
+
+pub fn home_dir() -> Result<PathBuf> {
+    dirs::home_dir().context("Could not find the home directory")
+}
+
+/// Expands a `~` at the start to the home directory and `$VAR` or `${VAR}`
+/// to the value of the variable, like a shell does.
+pub fn expand_path(value: &str, home: &Path, var: impl Fn(&str) -> Option<String>) -> Result<PathBuf, String> {
+    let (home, rest) = match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => (Some(home), rest.trim_start_matches('/')),
+        Some(_) => return Err(format!("\"{value}\" uses ~user, only ~ for your own home directory can be used")),
+        None => (None, value),
+    };
+
+    let mut expanded = String::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '$' {
+            expanded.push(ch);
+            continue;
+        }
+        let braced = chars.next_if_eq(&'{').is_some();
+        let mut name = String::new();
+        while let Some(ch) = chars.next_if(|x| x.is_ascii_alphanumeric() || *x == '_') {
+            name.push(ch);
+        }
+        if braced && (name.is_empty() || chars.next_if_eq(&'}').is_none()) {
+            return Err(format!("\"{value}\" has a `${{` without a variable name or its `}}`"));
+        }
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        let Some(val) = var(&name) else {
+            return Err(format!("\"{value}\" uses ${name}, which is not set"));
+        };
+        expanded.push_str(&val);
+    }
+
+    Ok(match home {
+        Some(home) if expanded.is_empty() => home.to_path_buf(),
+        Some(home) => home.join(expanded),
+        None => PathBuf::from(expanded),
+    })
+}
+
+/// The setting of a `GHI_*` variable with its value in the type of the
+/// config, e.g. `GHI_GRADING_TYPO_DISTANCE=2` or `GHI_KEYS_UP=k,ctrl-p`.
+fn env_setting(name: &str, value: &str) -> Option<Result<(String, Value), String>> {
+    let rest = name.strip_prefix("GHI_")?;
+    let key = if rest == "DATABASE_DIR" {
+        "database_dir".to_string()
+    } else {
+        let Some(key) = ["GRADING", "THEME", "KEYS", "EXAMPLES"].into_iter()
+            .find_map(|section| Some(format!("{section}.{}", rest.strip_prefix(section)?.strip_prefix('_')?)))
+        else {
+            return Some(Err(
+                "unknown setting, use GHI_DATABASE_DIR or a GHI_GRADING_*, GHI_THEME_*, GHI_KEYS_* or GHI_EXAMPLES_* variable".to_string()
+            ));
+        };
+        key.to_lowercase()
+    };
+
+    let value = match key.as_str() {
+        "grading.strict" | "grading.short_alias" => match value.trim() {
+            "true" | "1" => Ok(Value::Boolean(true)),
+            "false" | "0" => Ok(Value::Boolean(false)),
+            _ => Err(format!("\"{value}\" is not true or false")),
+        },
+        "grading.typo_distance" => value.trim().parse::<i64>()
+            .map(Value::Integer)
+            .map_err(|_| format!("\"{value}\" is not a number")),
+        _ if key.starts_with("keys.") || key.starts_with("examples.") => {
+            Ok(Value::Array(value.split(',').map(|x| Value::String(x.trim().to_string())).collect()))
+        },
+        _ => Ok(Value::String(value.to_string())),
+    };
+    Some(value.map(|value| (key, value)))
+}
+
+// The key `a.b` as the table `{ a = { b = value } }`
+fn nested(key: &str, value: Value) -> Table {
+    let mut parts = key.rsplit('.');
+    let mut table = Table::from_iter([(parts.next().unwrap_or_default().to_string(), value)]);
+    for part in parts {
+        table = Table::from_iter([(part.to_string(), Value::Table(table))]);
+    }
+    table
+}
+
+fn defaults() -> Table {
+    let tolerance = Tolerance::default();
+    let keys = KeyMap::default().bindings().into_iter()
+        .map(|(name, keys)| (name.to_string(), Value::Array(keys.into_iter().map(Value::String).collect())))
+        .collect();
+
+    Table::from_iter([
+        ("database_dir".to_string(), Value::String("~/.ghi".to_string())),
+        ("grading".to_string(), Value::Table(Table::from_iter([
+            ("strict".to_string(), Value::Boolean(false)),
+            ("typo_distance".to_string(), Value::Integer(tolerance.distance.try_into().unwrap_or_default())),
+            ("short_alias".to_string(), Value::Boolean(tolerance.short_alias)),
+        ]))),
+        ("theme".to_string(), Value::Table(Table::from_iter([
+            ("preset".to_string(), Value::String("dark".to_string())),
+        ]))),
+        ("keys".to_string(), Value::Table(keys)),
+    ])
+}
+
+/// The config put together from its layers, where a later one wins:
+///
+/// 1. the defaults
+/// 2. the first config file found, see `config_paths`
+/// 3. the `GHI_*` environment variables
+/// 4. the file of `--config`
+///
+/// Every value remembers the layer it comes from, for `ghi config show`.
+pub struct Layers {
+    home: PathBuf,
+    values: Table,
+    sources: BTreeMap<String, Source>,
+}
+
+impl Layers {
+    pub fn new(home: PathBuf) -> Self {
+        let mut layers = Self { home, values: Table::new(), sources: BTreeMap::new() };
+        layers.merge(defaults(), &Source::Default);
+        layers
+    }
+
+    /// All the layers of the running ghi.
+    pub fn load(flag: &Option<PathBuf>) -> Result<Self> {
+        let home = home_dir()?;
+        let mut layers = Self::new(home.clone());
+
+        if let Some(path) = config_paths(&home).into_iter().find(|x| x.exists()) {
+            layers.merge_file(&path)?;
+        }
+        let vars = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(name, _)| name.starts_with("GHI_"));
+        layers.merge_env(vars)?;
+        if let Some(path) = flag {
+            layers.merge_file(path)?;
+        }
+        layers.merge_no_color(window::no_color());
+
+        Ok(layers)
+    }
+
+    fn merge(&mut self, table: Table, source: &Source) {
+        merge_table(&mut self.values, table, "", source, &mut self.sources);
+    }
+
+    pub fn merge_str(&mut self, data: &str, source: &Source) -> Result<()> {
+        let invalid = |err: toml::de::Error| anyhow!("Invalid config {source}: {}", err.to_string().trim_end());
+        // Parsed into the config first, so the error points at the line
+        toml::from_str::<GhiUserConfig>(data).map_err(invalid)?;
+        self.merge(toml::from_str(data).map_err(invalid)?, source);
+        Ok(())
+    }
+
+    pub fn merge_file(&mut self, path: &Path) -> Result<()> {
+        tracing::info!("Parsing config from {:?}", path);
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read the config {}", path.display()))?;
+        self.merge_str(&data, &Source::File(path.to_path_buf()))
+    }
+
+    pub fn merge_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+        let mut vars: Vec<(String, String)> = vars.into_iter().collect();
+        vars.sort();
+        for (name, value) in vars {
+            let Some(setting) = env_setting(&name, &value) else {
+                continue;
+            };
+            let (key, value) = setting.map_err(|err| anyhow!("Invalid config ${name}: {err}"))?;
+            let table = nested(&key, value);
+            Value::Table(table.clone()).try_into::<GhiUserConfig>()
+                .map_err(|err| anyhow!("Invalid config ${name}: {}", err.to_string().trim_end()))?;
+            self.merge(table, &Source::Env(name));
+        }
+        Ok(())
+    }
+
+    // A theme set by the user wins over `NO_COLOR`, like https://no-color.org asks
+    pub fn merge_no_color(&mut self, no_color: bool) {
+        let has_theme = self.sources.iter().any(|(key, source)| key.starts_with("theme.") && *source != Source::Default);
+        if no_color && !has_theme {
+            self.merge(nested("theme.preset", Value::String("plain".to_string())), &Source::NoColor);
+        }
+    }
+
+    /// Every value by its key like `grading.typo_distance`, with where it
+    /// comes from.
+    pub fn settings(&self) -> Vec<(&str, &Value, &Source)> {
+        self.sources.iter()
+            .filter_map(|(key, source)| {
+                let mut value: Option<&Value> = None;
+                for part in key.split('.') {
+                    value = match value {
+                        None => self.values.get(part),
+                        Some(Value::Table(table)) => table.get(part),
+                        Some(_) => None,
+                    };
+                }
+                Some((key.as_str(), value?, source))
+            })
+            .collect()
+    }
+
+    fn invalid(&self, err: String) -> anyhow::Error {
+        // The errors start with the key of the value, like `keys.hint: ...`
+        let key = err.split(':').next().unwrap_or_default();
+        match self.sources.get(key) {
+            Some(source) => anyhow!("Invalid config {source}: {err}"),
+            None => anyhow!("Invalid config: {err}"),
+        }
+    }
+
+    pub fn resolve(&self) -> Result<GhiConfig> {
+        let user_config: GhiUserConfig = Value::Table(self.values.clone()).try_into()
+            .map_err(|err| anyhow!("Invalid config: {}", err.to_string().trim_end()))?;
+
+        let database_dir = match &user_config.database_dir {
+            Some(dir) => expand_path(dir, &self.home, |name| std::env::var(name).ok())
+                .map_err(|err| self.invalid(format!("database_dir: {err}")))?,
+            None => self.home.join(".ghi"),
+        };
+        let theme = Theme::parse(&user_config.theme.unwrap_or_default()).map_err(|err| self.invalid(err))?;
+        let keys = parse_keys(&user_config.keys).map_err(|err| self.invalid(err))?;
+
+        Ok(GhiConfig {
+            config_dir: self.home.join(".ghi"),
+            database_dir,
+            tolerance: parse_tolerance(&user_config.grading),
+            examples: user_config.examples.unwrap_or_default(),
+            theme,
+            color: self.sources.get("theme.preset") != Some(&Source::NoColor),
+            keys,
+        })
+    }
+}
+
+fn merge_table(into: &mut Table, from: Table, prefix: &str, source: &Source, sources: &mut BTreeMap<String, Source>) {
+    for (key, value) in from {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+        match (into.get_mut(&key), value) {
+            (Some(Value::Table(into)), Value::Table(from)) => merge_table(into, from, &path, source, sources),
+            (_, Value::Table(from)) => {
+                sources.remove(&path);
+                let mut table = Table::new();
+                merge_table(&mut table, from, &path, source, sources);
+                into.insert(key, Value::Table(table));
+            },
+            (_, value) => {
+                sources.insert(path, source.clone());
+                into.insert(key, value);
+            },
+        }
+    }
+}
+
+/// The config of the running ghi.
+pub fn get_config(flag: &Option<PathBuf>) -> Result<GhiConfig> {
+    Layers::load(flag)?.resolve()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> Source {
+        Source::File(PathBuf::from(name))
+    }
+
+    #[test]
+    fn expand_paths() {
+        let home = Path::new("/home/sam");
+        let var = |name: &str| (name == "DATA").then(|| "/mnt/data".to_string());
+
+        assert_eq!(expand_path("~", home, var), Ok(PathBuf::from("/home/sam")));
+        assert_eq!(expand_path("~/", home, var), Ok(PathBuf::from("/home/sam")));
+        assert_eq!(expand_path("~/sync/ghi", home, var), Ok(PathBuf::from("/home/sam/sync/ghi")));
+        assert_eq!(expand_path("$DATA/ghi", home, var), Ok(PathBuf::from("/mnt/data/ghi")));
+        assert_eq!(expand_path("${DATA}-ghi", home, var), Ok(PathBuf::from("/mnt/data-ghi")));
+        assert_eq!(expand_path("/srv/$/ghi~", home, var), Ok(PathBuf::from("/srv/$/ghi~")));
+        assert!(expand_path("~sam/ghi", home, var).unwrap_err().contains("~user"));
+        assert!(expand_path("$HOME/ghi", home, var).unwrap_err().contains("$HOME, which is not set"));
+        assert!(expand_path("${DATA/ghi", home, var).is_err());
+    }
+
+    #[test]
+    fn layers_win_in_order() {
+        let mut layers = Layers::new(PathBuf::from("/home/sam"));
+        layers.merge_str("database_dir = \"~/sync\"\n[grading]\ntypo_distance = 2\n[theme]\ncommand = \"blue\"", &file("a.toml")).unwrap();
+        layers.merge_env([
+            ("GHI_GRADING_TYPO_DISTANCE".to_string(), "3".to_string()),
+            ("GHI_KEYS_UP".to_string(), "k, ctrl-p".to_string()),
+        ]).unwrap();
+        layers.merge_str("[theme]\npreset = \"light\"", &file("b.toml")).unwrap();
+        layers.merge_no_color(true);
+
+        let settings = layers.settings();
+        let find = |key: &str| settings.iter().find(|x| x.0 == key).map(|x| (x.1.to_string(), x.2.clone()));
+        assert_eq!(find("database_dir"), Some(("\"~/sync\"".to_string(), file("a.toml"))));
+        assert_eq!(find("grading.typo_distance"), Some(("3".to_string(), Source::Env("GHI_GRADING_TYPO_DISTANCE".to_string()))));
+        assert_eq!(find("grading.strict"), Some(("false".to_string(), Source::Default)));
+        assert_eq!(find("theme.preset"), Some(("\"light\"".to_string(), file("b.toml"))));
+        assert_eq!(find("theme.command"), Some(("\"blue\"".to_string(), file("a.toml"))));
+
+        let config = layers.resolve().unwrap();
+        assert_eq!(config.database_dir, PathBuf::from("/home/sam/sync"));
+        assert_eq!(config.tolerance.distance, 3);
+        assert_eq!(config.theme.text, Theme::light().text);
+        assert!(config.color);
+        assert_eq!(config.keys.label(window::Action::Up), "k");
+    }
+
+    #[test]
+    fn no_color_without_theme() {
+        let mut layers = Layers::new(PathBuf::from("/home/sam"));
+        layers.merge_no_color(true);
+        let config = layers.resolve().unwrap();
+        assert_eq!(config.theme, Theme::plain());
+        assert!(!config.color);
+
+        let config = Layers::new(PathBuf::from("/home/sam")).resolve().unwrap();
+        assert_eq!(config.theme, Theme::default());
+        assert_eq!(config.keys, KeyMap::default());
+        assert_eq!(config.database_dir, PathBuf::from("/home/sam/.ghi"));
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let mut layers = Layers::new(PathBuf::from("/home/sam"));
+        let err = layers.merge_str("[grading]\ntypo_distance = \"two\"", &file("a.toml")).unwrap_err().to_string();
+        assert!(err.starts_with("Invalid config a.toml: TOML parse error at line 2"), "{err}");
+
+        let err = layers.merge_str("databse_dir = \"~\"", &file("a.toml")).unwrap_err().to_string();
+        assert!(err.contains("unknown field `databse_dir`"), "{err}");
+
+        let err = layers.merge_env([("GHI_GRADING_STRICT".to_string(), "yes".to_string())]).unwrap_err().to_string();
+        assert_eq!(err, "Invalid config $GHI_GRADING_STRICT: \"yes\" is not true or false");
+
+        let err = layers.merge_env([("GHI_DATABASE".to_string(), "~".to_string())]).unwrap_err().to_string();
+        assert!(err.starts_with("Invalid config $GHI_DATABASE: unknown setting"), "{err}");
+
+        layers.merge_str("[keys]\nhint = \"h\"", &file("b.toml")).unwrap();
+        let err = layers.resolve().err().unwrap().to_string();
+        assert!(err.starts_with("Invalid config b.toml: keys.hint: \"h\" would be typed"), "{err}");
+    }
+}
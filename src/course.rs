@@ -0,0 +1,199 @@
+use std::fmt;
+use anyhow::{bail, Result};
+
+/// A course file is a header with the command it teaches and a list of
+/// quests separated by `=` lines.
+///
+/// Name: Git speedup
+/// Description: A collection of usefull git commands
+/// Command: git
+///
+/// =
+///
+/// Description: Create a branch and switch to it
+/// Schema: git switch -c <branch>
+/// Alternative: git checkout -b <branch>
+/// Tags: branches, daily
+#[derive(Debug, PartialEq)]
+pub struct Course {
+    pub name: String,
+    pub description: String,
+    pub command: String,
+    pub quests: Vec<CourseQuest>,
+}
+
+/// - line: where the `Schema:` is in the file, to point at problems
+/// - alternatives: the other accepted patterns with their lines
+/// - tags: to find the quest with `ghi list --tag`
+#[derive(Debug, PartialEq)]
+pub struct CourseQuest {
+    pub description: String,
+    pub schema: String,
+    pub line: usize,
+    pub alternatives: Vec<(String, usize)>,
+    pub tags: Vec<String>,
+}
+
+/// Writes the course back in the file format, e.g. to export a lesson.
+impl fmt::Display for Course {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(f, "Description: {}", self.description)?;
+        writeln!(f, "Command: {}", self.command)?;
+
+        for quest in &self.quests {
+            writeln!(f, "\n=\n")?;
+            writeln!(f, "Description: {}", quest.description)?;
+            writeln!(f, "Schema: {}", quest.schema)?;
+            for (alternative, _) in &quest.alternatives {
+                writeln!(f, "Alternative: {alternative}")?;
+            }
+            if !quest.tags.is_empty() {
+                writeln!(f, "Tags: {}", quest.tags.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The value of a `Key: value` line.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    line.strip_prefix(key)
+        .and_then(|x| x.strip_prefix(':'))
+        .map(|x| x.trim())
+}
+
+pub fn parse(src: &str) -> Result<Course> {
+    let mut course = Course {
+        name: String::new(),
+        description: String::new(),
+        command: String::new(),
+        quests: Vec::new(),
+    };
+    let mut in_header = true;
+    let mut description = None;
+
+    for (idx, line) in src.lines().enumerate() {
+        let line_nr = idx + 1;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+        if line == "=" {
+            in_header = false;
+            continue;
+        }
+
+        if in_header {
+            if let Some(value) = field(line, "Name") {
+                course.name = value.to_string();
+            } else if let Some(value) = field(line, "Description") {
+                course.description = value.to_string();
+            } else if let Some(value) = field(line, "Command") {
+                course.command = value.to_string();
+            } else {
+                bail!("Line {line_nr}: unknown course field '{line}'");
+            }
+        } else if let Some(value) = field(line, "Description") {
+            description = Some(value.to_string());
+        } else if let Some(value) = field(line, "Schema") {
+            let Some(description) = description.take() else {
+                bail!("Line {line_nr}: 'Schema:' needs a 'Description:' before it");
+            };
+            course.quests.push(CourseQuest {
+                description,
+                schema: value.to_string(),
+                line: line_nr,
+                alternatives: Vec::new(),
+                tags: Vec::new(),
+            });
+        } else if let Some(value) = field(line, "Alternative") {
+            match course.quests.last_mut() {
+                Some(quest) if description.is_none() => quest.alternatives.push((value.to_string(), line_nr)),
+                _ => bail!("Line {line_nr}: 'Alternative:' needs a 'Schema:' before it"),
+            }
+        } else if let Some(value) = field(line, "Tags") {
+            match course.quests.last_mut() {
+                Some(quest) if description.is_none() => {
+                    let tags = value.split(',').map(|x| x.trim()).filter(|x| !x.is_empty());
+                    quest.tags.extend(tags.map(|x| x.to_string()));
+                },
+                _ => bail!("Line {line_nr}: 'Tags:' needs a 'Schema:' before it"),
+            }
+        } else {
+            bail!("Line {line_nr}: unknown quest field '{line}'");
+        }
+    }
+
+    if course.command.is_empty() {
+        bail!("The course needs a 'Command:' in the header");
+    }
+
+    Ok(course)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_course() {
+        let src = "Name: Git\nDescription: Git things\nCommand: git\n\n=\n\nDescription: Stash list\nSchema: git stash list\n\n=\n\nDescription: Amend\nSchema: git commit --amend\n";
+        let course = parse(src).unwrap();
+
+        assert_eq!(course.name, "Git");
+        assert_eq!(course.command, "git");
+        assert_eq!(course.quests, vec![
+            CourseQuest {
+                description: "Stash list".to_string(),
+                schema: "git stash list".to_string(),
+                line: 8,
+                alternatives: vec![],
+                tags: vec![],
+            },
+            CourseQuest {
+                description: "Amend".to_string(),
+                schema: "git commit --amend".to_string(),
+                line: 13,
+                alternatives: vec![],
+                tags: vec![],
+            },
+        ]);
+    }
+
+    #[test]
+    fn parse_alternatives() {
+        let src = "Command: git\n=\nDescription: New branch\nSchema: git switch -c <branch>\nAlternative: git checkout -b <branch>\n";
+        let course = parse(src).unwrap();
+
+        assert_eq!(course.quests[0].alternatives, vec![("git checkout -b <branch>".to_string(), 5)]);
+    }
+
+    #[test]
+    fn parse_tags() {
+        let src = "Command: git\n=\nDescription: New branch\nSchema: git switch -c <branch>\nTags: branches, daily,\n";
+        let course = parse(src).unwrap();
+
+        assert_eq!(course.quests[0].tags, vec!["branches".to_string(), "daily".to_string()]);
+    }
+
+    #[test]
+    fn write_course() {
+        let src = "Name: Git\nDescription: Git things\nCommand: git\n\n=\n\nDescription: New branch\nSchema: git switch -c <branch>\nAlternative: git checkout -b <branch>\nTags: branches, daily\n";
+        let course = parse(src).unwrap();
+
+        assert_eq!(course.to_string(), src);
+        assert_eq!(parse(&course.to_string()).unwrap(), course);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("Name: Git\n").is_err());
+        assert!(parse("Command: git\n=\nSchema: git log\n").is_err());
+        assert!(parse("Command: git\n=\nDescription: log\nOutput: x\n").is_err());
+        assert!(parse("Command: git\n=\nAlternative: git log\n").is_err());
+        assert!(parse("Command: git\n=\nTags: log\n").is_err());
+    }
+}
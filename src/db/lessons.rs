@@ -1,7 +1,8 @@
 use diesel::SqliteConnection;
 use diesel::prelude::*;
 use super::schema::lessons::dsl::*;
-use super::models::Lesson;
+use super::models::{Lesson, NewLesson};
+use super::last_insert_rowid;
 
 #[tracing::instrument(name = "Query all lessons", skip(conn))]
 pub fn query_all_lessons(conn: &mut SqliteConnection) -> Vec<Lesson> {
@@ -17,3 +18,24 @@ pub fn query_all_lessons(conn: &mut SqliteConnection) -> Vec<Lesson> {
     }
 }
 
+
+/// The ids and names of all the lessons.
+#[tracing::instrument(name = "Query lesson names", skip(conn))]
+pub fn query_lesson_names(conn: &mut SqliteConnection) -> Vec<(i32, String)> {
+    match lessons.select((id, name)).order(id).get_results(conn) {
+        Ok(res) => res,
+        Err(e) => {
+            tracing::error!("Failed to query lesson names: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Saves a new lesson and returns its id, read back with `last_insert_rowid`
+/// of the connection.
+pub fn insert_lesson(conn: &mut SqliteConnection, lesson: &NewLesson) -> QueryResult<i32> {
+    diesel::insert_into(lessons)
+        .values(lesson)
+        .execute(conn)?;
+    diesel::select(last_insert_rowid()).get_result(conn)
+}
@@ -9,6 +9,9 @@ use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use log::{info, debug};
 use super::GhiConfig;
 
+// The id of the row that the connection inserted last.
+sql_function!(fn last_insert_rowid() -> diesel::sql_types::Integer);
+
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
 
 pub fn establish_connection(config: &GhiConfig) -> SqliteConnection {
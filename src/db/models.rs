@@ -1,8 +1,10 @@
 use diesel::prelude::*;
 use chrono::NaiveDateTime;
 use crate::db::schema::quests;
+use crate::db::schema::quest_patterns;
 use crate::db::schema::lessons;
 use crate::db::schema::bucket;
+use crate::db::schema::attempts;
 
 #[derive(Debug)]
 #[derive(Queryable, Selectable)]
@@ -21,7 +23,15 @@ pub struct Quest {
     pub miss_count: i32,
     pub created_at: NaiveDateTime, 
     pub updated_at: NaiveDateTime, 
-    pub lesson_id: i32
+    pub lesson_id: i32,
+    pub almost_count: i32,
+    // The answers of `ghi run --reverse`, kept apart from the ones above
+    pub reverse_display_count: i32,
+    pub reverse_ok_count: i32,
+    pub reverse_miss_count: i32,
+    // When to ask the quest again, never asked ones have no date
+    pub interval_days: i32,
+    pub due_at: Option<NaiveDateTime>,
 }
 
 #[derive(Insertable)]
@@ -35,6 +45,14 @@ pub struct NewQuest<'a> {
     pub lesson_id: i32,
 }
 
+/// Another pattern that answers the quest, next to its `Quest.pattern`.
+#[derive(Insertable)]
+#[diesel(table_name = quest_patterns)]
+pub struct NewQuestPattern<'a> {
+    pub quest_id: i32,
+    pub pattern: &'a str,
+}
+
 #[derive(Debug)]
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = lessons)]
@@ -77,3 +95,21 @@ pub struct NewBucket<'a> {
     pub notes: Option<&'a str>,
 }
 
+
+/// One answer to a quest in `ghi run`.
+///
+/// - first_key_ms: how long until the first key was pressed (none if never)
+/// - submit_ms: how long until the answer was submitted or the time ran out
+/// - input: the answer as it was typed
+/// - hints_used: how many words of the answer were shown as a hint
+#[derive(Insertable)]
+#[diesel(table_name = attempts)]
+pub struct NewAttempt<'a> {
+    pub quest_id: i32,
+    pub mode: &'a str,
+    pub grade: &'a str,
+    pub first_key_ms: Option<i32>,
+    pub submit_ms: i32,
+    pub input: &'a str,
+    pub hints_used: i32,
+}
@@ -1,7 +1,14 @@
 use diesel::prelude::*;
 use diesel::SqliteConnection;
 use super::schema::quests::dsl;
-use super::models::Quest;
+use super::schema::quest_patterns;
+use super::schema::quest_node_stats;
+use super::schema::quest_tags;
+use chrono::NaiveDateTime;
+use super::schema::attempts;
+use super::last_insert_rowid;
+use super::models::{NewAttempt, NewQuest, NewQuestPattern, Quest};
+use crate::parser::compare::{Grade, Matched};
 
 #[tracing::instrument(name = "Query quests", skip(conn))]
 pub fn query_quests(conn: &mut SqliteConnection, lesson_id: i32) -> Vec<Quest> {
@@ -37,3 +44,247 @@ pub fn query_quest(conn: &mut SqliteConnection, id: i32) -> Option<Quest> {
             }
         }
 }
+
+#[tracing::instrument(name = "Query all quests", skip(conn))]
+pub fn query_all_quests(conn: &mut SqliteConnection) -> Vec<Quest> {
+    match dsl::quests
+        .filter(dsl::pattern.is_not(""))
+        .get_results(conn)
+        {
+            Ok(res) => {
+                tracing::info!("Query all quests has been successful");
+                res
+            },
+            Err(e) => {
+                tracing::error!("Failed to execute query all quests: {}", e);
+                vec![]
+            }
+        }
+}
+
+/// The quests that are due by `due_by`, or all of them without it, with
+/// the ones due first. Quests that were never asked have no due date, so
+/// they are always due and come before all the others.
+#[tracing::instrument(name = "Query scheduled quests", skip(conn))]
+pub fn query_scheduled_quests(conn: &mut SqliteConnection, due_by: Option<NaiveDateTime>) -> Vec<Quest> {
+    let mut query = dsl::quests
+        .filter(dsl::pattern.is_not(""))
+        .order((dsl::due_at.asc(), dsl::id.asc()))
+        .into_boxed();
+    if let Some(due_by) = due_by {
+        query = query.filter(dsl::due_at.is_null().or(dsl::due_at.le(due_by)));
+    }
+
+    match query.get_results(conn)
+        {
+            Ok(res) => {
+                tracing::info!("Query scheduled quests has been successful");
+                res
+            },
+            Err(e) => {
+                tracing::error!("Failed to execute query scheduled quests: {}", e);
+                vec![]
+            }
+        }
+}
+
+/// The other accepted patterns of a quest, without its main `pattern`.
+#[tracing::instrument(name = "Query quest patterns", skip(conn))]
+pub fn query_quest_patterns(conn: &mut SqliteConnection, quest_id: i32) -> Vec<String> {
+    match quest_patterns::table
+        .filter(quest_patterns::quest_id.eq(quest_id))
+        .order(quest_patterns::id)
+        .select(quest_patterns::pattern)
+        .get_results(conn)
+        {
+            Ok(res) => res,
+            Err(e) => {
+                tracing::error!("Failed to execute query quest patterns: {}", e);
+                vec![]
+            }
+        }
+}
+
+/// Saves a quest together with its other accepted patterns and returns
+/// the id of the new quest.
+#[tracing::instrument(name = "Insert quest", skip(conn, quest))]
+pub fn insert_quest(conn: &mut SqliteConnection, quest: &NewQuest, alternatives: &[String]) -> QueryResult<i32> {
+    conn.transaction(|conn| {
+        diesel::insert_into(dsl::quests)
+            .values(quest)
+            .execute(conn)?;
+        let quest_id: i32 = diesel::select(last_insert_rowid()).get_result(conn)?;
+
+        for pattern in alternatives {
+            diesel::insert_into(quest_patterns::table)
+                .values(&NewQuestPattern { quest_id, pattern })
+                .execute(conn)?;
+        }
+
+        Ok(quest_id)
+    })
+}
+
+/// The tags of a quest, like `branches` or `daily`.
+#[tracing::instrument(name = "Query quest tags", skip(conn))]
+pub fn query_quest_tags(conn: &mut SqliteConnection, quest_id: i32) -> Vec<String> {
+    match quest_tags::table
+        .filter(quest_tags::quest_id.eq(quest_id))
+        .order(quest_tags::tag)
+        .select(quest_tags::tag)
+        .get_results(conn)
+        {
+            Ok(res) => res,
+            Err(e) => {
+                tracing::error!("Failed to execute query quest tags: {}", e);
+                vec![]
+            }
+        }
+}
+
+/// Adds tags to a quest. A tag the quest already has is kept once.
+#[tracing::instrument(name = "Insert quest tags", skip(conn))]
+pub fn insert_quest_tags(conn: &mut SqliteConnection, quest_id: i32, tags: &[String]) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        for tag in tags {
+            diesel::insert_into(quest_tags::table)
+                .values((quest_tags::quest_id.eq(quest_id), quest_tags::tag.eq(tag)))
+                .on_conflict((quest_tags::quest_id, quest_tags::tag))
+                .do_nothing()
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+/// Count the answer of a quest into its stats. A near miss is counted
+/// on its own so it does not look like the quest was never learned.
+#[tracing::instrument(name = "Record grade", skip(conn))]
+pub fn record_grade(conn: &mut SqliteConnection, id: i32, grade: Grade) -> QueryResult<usize> {
+    let quest = diesel::update(dsl::quests.find(id));
+    let res = match grade {
+        Grade::Correct => quest
+            .set((dsl::display_count.eq(dsl::display_count + 1), dsl::ok_count.eq(dsl::ok_count + 1)))
+            .execute(conn),
+        Grade::Almost => quest
+            .set((dsl::display_count.eq(dsl::display_count + 1), dsl::almost_count.eq(dsl::almost_count + 1)))
+            .execute(conn),
+        Grade::Wrong => quest
+            .set((dsl::display_count.eq(dsl::display_count + 1), dsl::miss_count.eq(dsl::miss_count + 1)))
+            .execute(conn),
+    };
+
+    if let Err(e) = &res {
+        tracing::error!("Failed to record grade: {}", e);
+    }
+    res
+}
+
+/// Count the answer of a reverse quest, where the description is picked
+/// for a command. Those stats are kept apart from the forward ones.
+#[tracing::instrument(name = "Record reverse grade", skip(conn))]
+pub fn record_reverse_grade(conn: &mut SqliteConnection, id: i32, grade: Grade) -> QueryResult<usize> {
+    let quest = diesel::update(dsl::quests.find(id));
+    let res = match grade {
+        Grade::Correct => quest
+            .set((dsl::reverse_display_count.eq(dsl::reverse_display_count + 1), dsl::reverse_ok_count.eq(dsl::reverse_ok_count + 1)))
+            .execute(conn),
+        Grade::Almost | Grade::Wrong => quest
+            .set((dsl::reverse_display_count.eq(dsl::reverse_display_count + 1), dsl::reverse_miss_count.eq(dsl::reverse_miss_count + 1)))
+            .execute(conn),
+    };
+
+    if let Err(e) = &res {
+        tracing::error!("Failed to record reverse grade: {}", e);
+    }
+    res
+}
+
+/// Counts the grade of each word of an answer, so the practice can focus
+/// on the parts of a command that are missed the most. A near miss counts
+/// as a miss here.
+#[tracing::instrument(name = "Record node grades", skip(conn, matched))]
+pub fn record_node_grades(conn: &mut SqliteConnection, quest_id: i32, matched: &[Matched]) -> QueryResult<()> {
+    use quest_node_stats::dsl as nodes;
+
+    let res = conn.transaction(|conn| {
+        for item in matched {
+            let (ok, miss) = if item.grade == Grade::Correct { (1, 0) } else { (0, 1) };
+            diesel::insert_into(nodes::quest_node_stats)
+                .values((
+                    nodes::quest_id.eq(quest_id),
+                    nodes::node.eq(&item.value),
+                    nodes::ok_count.eq(ok),
+                    nodes::miss_count.eq(miss),
+                ))
+                .on_conflict((nodes::quest_id, nodes::node))
+                .do_update()
+                .set((nodes::ok_count.eq(nodes::ok_count + ok), nodes::miss_count.eq(nodes::miss_count + miss)))
+                .execute(conn)?;
+        }
+        Ok(())
+    });
+
+    if let Err(e) = &res {
+        tracing::error!("Failed to record node grades: {}", e);
+    }
+    res
+}
+
+/// The `(node, ok_count, miss_count)` of the words of a quest.
+#[tracing::instrument(name = "Query node stats", skip(conn))]
+pub fn query_node_stats(conn: &mut SqliteConnection, quest_id: i32) -> Vec<(String, i32, i32)> {
+    use quest_node_stats::dsl as nodes;
+
+    match nodes::quest_node_stats
+        .filter(nodes::quest_id.eq(quest_id))
+        .select((nodes::node, nodes::ok_count, nodes::miss_count))
+        .get_results(conn)
+        {
+            Ok(res) => res,
+            Err(e) => {
+                tracing::error!("Failed to execute query node stats: {}", e);
+                vec![]
+            }
+        }
+}
+
+#[tracing::instrument(name = "Record schedule", skip(conn))]
+pub fn record_schedule(conn: &mut SqliteConnection, id: i32, interval_days: i32, due_at: NaiveDateTime) -> QueryResult<usize> {
+    let res = diesel::update(dsl::quests.find(id))
+        .set((dsl::interval_days.eq(interval_days), dsl::due_at.eq(Some(due_at))))
+        .execute(conn);
+
+    if let Err(e) = &res {
+        tracing::error!("Failed to record schedule: {}", e);
+    }
+    res
+}
+
+#[tracing::instrument(name = "Record attempt", skip(conn, attempt))]
+pub fn record_attempt(conn: &mut SqliteConnection, attempt: &NewAttempt) -> QueryResult<usize> {
+    let res = diesel::insert_into(attempts::table)
+        .values(attempt)
+        .execute(conn);
+
+    if let Err(e) = &res {
+        tracing::error!("Failed to record attempt: {}", e);
+    }
+    res
+}
+
+/// The `(quest_id, grade, created_at)` of all the attempts, oldest first.
+#[tracing::instrument(name = "Query attempt history", skip(conn))]
+pub fn query_attempt_history(conn: &mut SqliteConnection) -> Vec<(i32, String, NaiveDateTime)> {
+    match attempts::table
+        .select((attempts::quest_id, attempts::grade, attempts::created_at))
+        .order(attempts::id)
+        .get_results(conn)
+        {
+            Ok(res) => res,
+            Err(e) => {
+                tracing::error!("Failed to execute query attempt history: {}", e);
+                vec![]
+            }
+        }
+}
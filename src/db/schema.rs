@@ -1,5 +1,19 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    attempts (id) {
+        id -> Integer,
+        quest_id -> Integer,
+        mode -> Text,
+        grade -> Text,
+        first_key_ms -> Nullable<Integer>,
+        submit_ms -> Integer,
+        created_at -> Timestamp,
+        input -> Text,
+        hints_used -> Integer,
+    }
+}
+
 diesel::table! {
     bucket (id) {
         id -> Integer,
@@ -22,6 +36,33 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    quest_node_stats (id) {
+        id -> Integer,
+        quest_id -> Integer,
+        node -> Text,
+        ok_count -> Integer,
+        miss_count -> Integer,
+    }
+}
+
+diesel::table! {
+    quest_patterns (id) {
+        id -> Integer,
+        quest_id -> Integer,
+        pattern -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    quest_tags (id) {
+        id -> Integer,
+        quest_id -> Integer,
+        tag -> Text,
+    }
+}
+
 diesel::table! {
     quests (id) {
         id -> Integer,
@@ -37,13 +78,27 @@ diesel::table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         lesson_id -> Integer,
+        almost_count -> Integer,
+        reverse_display_count -> Integer,
+        reverse_ok_count -> Integer,
+        reverse_miss_count -> Integer,
+        interval_days -> Integer,
+        due_at -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(attempts -> quests (quest_id));
+diesel::joinable!(quest_node_stats -> quests (quest_id));
+diesel::joinable!(quest_patterns -> quests (quest_id));
+diesel::joinable!(quest_tags -> quests (quest_id));
 diesel::joinable!(quests -> lessons (lesson_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    attempts,
     bucket,
     lessons,
+    quest_node_stats,
+    quest_patterns,
+    quest_tags,
     quests,
 );
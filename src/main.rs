@@ -2,12 +2,15 @@ mod parser;
 mod commands;
 mod window;
 mod db;
+mod course;
+mod schedule;
+mod config;
 
 use clap::{Parser, Subcommand};
-use dirs;
 use std::path::PathBuf;
-use serde::Deserialize;
+use anyhow::Context;
 use tracing_subscriber::prelude::*;
+use config::GhiConfig;
 
 #[derive(Parser)]
 #[command(author = "Sam Uherek <samuherekbiz@gmail.com>")]
@@ -16,6 +19,9 @@ use tracing_subscriber::prelude::*;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
+    /// Read this config file over the other config files
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -26,73 +32,83 @@ pub enum Commands {
     Bucket { value: Option<String>},
     /// Start an explorer to see all the commands 
     Explore,
+    /// Print the saved quests
+    List {
+        /// Only the quests of this lesson
+        #[arg(long)]
+        lesson: Option<String>,
+        /// Only the quests with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        #[arg(long, value_enum, default_value_t = commands::list::Format::Table)]
+        format: commands::list::Format,
+    },
+    /// Print the cheat sheet of a command, e.g. `ghi show git`
+    Show { cmd: String },
+    /// Fuzzy find quests by their pattern, question or notes
+    Search {
+        query: String,
+        #[arg(long, value_enum, default_value_t = commands::list::Format::Table)]
+        format: commands::list::Format,
+    },
     /// Start the flashcard game
-    Run
+    Run {
+        /// Show the command and pick what it does
+        #[arg(long)]
+        reverse: bool,
+        /// Show the command with one word blanked out to fill in
+        #[arg(long, conflicts_with = "reverse")]
+        cloze: bool,
+        /// Ask with example values for the variables, like a real command
+        #[arg(long)]
+        examples: bool,
+        /// Answer each quest within this many seconds
+        #[arg(long, value_name = "SECS")]
+        timed: Option<u64>,
+        /// Count the right answers in a row and stop at the first miss
+        #[arg(long)]
+        streak: bool,
+        /// Practice all the quests, not only the ones that are due
+        #[arg(long)]
+        all: bool,
+    },
+    /// Check the patterns of a course file or of all the saved quests
+    Lint { file: Option<PathBuf> },
+    /// Draft a lesson from a `--help` or man page text (a file or stdin)
+    IngestHelp { file: Option<PathBuf> },
+    /// Show how the practice is going
+    Stats {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Save a course file as a new lesson
+    Import { file: PathBuf },
+    /// Write a lesson as a course file (to a file or stdout)
+    Export {
+        #[arg(long, default_value = "default")]
+        lesson: String,
+        file: Option<PathBuf>,
+    },
+    /// Print, find or edit the config
+    Config {
+        #[command(subcommand)]
+        action: commands::config::ConfigAction,
+    },
 }
 
-struct GhiConfig {
-    // - $HOME/.ghi
-    config_dir: PathBuf,
-    // - $HOME/.ghirc
-    // - $HOME/.ghi/config
-    // - $HOME/.config/ghi/config
-    database_dir: PathBuf,
-}
-
-#[derive(Deserialize)]
-struct GhiUserConfig {
-   database_dir: Option<String>,
-}
-
-const CONFIG_PATHS: [&str; 2] = [".ghi/config.toml", ".config/ghi/config.toml"];
-
-fn parse_database_dir(home: &PathBuf, value: &Option<String>) -> Option<PathBuf> {
-    if let Some(val) = value {
-        if val.starts_with("~") {
-            let relative_path = &val[2..];
-            return Some(home.join(relative_path));
-        } 
-        return Some(PathBuf::from(val.to_string()));
+fn main() -> anyhow::Result<()>{
+    let cli = Cli::parse();
+    // The config can be fixed with `ghi config edit` even when it is invalid
+    if let Some(Commands::Config{action}) = &cli.command {
+        return commands::config::run(&cli.config, action);
     }
-    None
-}
-
-fn get_config() -> GhiConfig {
-    let home = dirs::home_dir().expect("Could not resolve home directory");
-    let first_config_path = CONFIG_PATHS.iter()
-        .map(|path| home.join(path))
-        .find(|path| path.exists());
 
-    let config = if let Some(first_config_path) = first_config_path {
-        tracing::info!("Parsing config from {:?}", first_config_path);
-        let data = std::fs::read_to_string(first_config_path).expect("The config path to exist");
-        let user_config: GhiUserConfig = toml::from_str(&data).expect("Toml config could not parse");
-        let database_dir = if let Some(dir) = parse_database_dir(&home, &user_config.database_dir) {
-            tracing::info!("Resolved custom databse directory.");
-            dir
-        } else {
-            tracing::info!("Could not resolve database dir. Using default.");
-            home.join(".ghi")
-        };
-
-        GhiConfig {
-            config_dir: home.join(".ghi"),
-            database_dir,
-        }
-    } else {
-        tracing::info!("Using default config");
-        GhiConfig {
-            config_dir: home.join(".ghi"),
-            database_dir: home.join(".ghi")
-        }
-    };
-
-    config 
-}
-
-fn main() -> anyhow::Result<()>{
-    let config = get_config();
-    let log_file = std::fs::File::create(config.config_dir.join("logs.txt")).expect("Could not create the log file");
+    let config = config::get_config(&cli.config)?;
+    std::fs::create_dir_all(&config.config_dir)
+        .with_context(|| format!("Could not create {}", config.config_dir.display()))?;
+    let log_file = std::fs::File::create(config.config_dir.join("logs.txt"))
+        .with_context(|| format!("Could not create the log file in {}", config.config_dir.display()))?;
 
     let subscriber = tracing_subscriber::registry()
         .with(tracing_subscriber::fmt::Layer::new()
@@ -104,14 +120,30 @@ fn main() -> anyhow::Result<()>{
     
     tracing::info!("Application started");
 
-    let cli = Cli::parse();
     let mut conn = db::establish_connection(&config);
 
     match &cli.command {
-        Some(Commands::Add{value}) => commands::add::run(&mut conn, value)?,
+        Some(Commands::Add{value}) => commands::add::run(&mut conn, &config, value)?,
         Some(Commands::Bucket{value}) => commands::bucket::run(&mut conn, value)?,
-        Some(Commands::Explore) => commands::explore::run(&mut conn)?,
-        Some(Commands::Run) => commands::run::run()?,
+        Some(Commands::Explore) => commands::explore::run(&mut conn, &config)?,
+        Some(Commands::List{lesson, tag, format}) => commands::list::run(&mut conn, lesson, tag, *format)?,
+        Some(Commands::Show{cmd}) => commands::show::run(&mut conn, &config, cmd)?,
+        Some(Commands::Search{query, format}) => commands::search::run(&mut conn, query, *format)?,
+        Some(Commands::Run{reverse, cloze, examples, timed, streak, all}) => {
+            let mode = match (reverse, cloze) {
+                (true, _) => commands::run::Mode::Reverse,
+                (_, true) => commands::run::Mode::Cloze,
+                _ => commands::run::Mode::Forward,
+            };
+            let options = commands::run::RunOptions { mode, examples: *examples, timed: *timed, streak: *streak, all: *all };
+            commands::run::run(&mut conn, &config, &options)?
+        },
+        Some(Commands::Lint{file}) => commands::lint::run(&mut conn, &config, file)?,
+        Some(Commands::IngestHelp{file}) => commands::ingest_help::run(&mut conn, file)?,
+        Some(Commands::Stats{json}) => commands::stats::run(&mut conn, &config, *json)?,
+        Some(Commands::Import{file}) => commands::import::run(&mut conn, file)?,
+        Some(Commands::Export{lesson, file}) => commands::export::run(&mut conn, lesson, file)?,
+        Some(Commands::Config{..}) => unreachable!(),
         None => {
             unimplemented!();
         }
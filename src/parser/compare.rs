@@ -1,6 +1,360 @@
-use crate::parser::parser::CmdWord;
+use std::collections::HashMap;
+use std::fmt;
+use crate::parser::parser::{stages, CmdWord, Connector, Variable};
 use crate::parser::input_lexer::Token;
 
+/// How close an answer, or one word of it, is to the pattern.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Grade {
+    Correct,
+    Almost,
+    Wrong,
+}
+
+impl Grade {
+    /// The grade of a whole answer is the worst grade of any of its parts.
+    pub fn of(matched: &[Matched]) -> Grade {
+        if matched.iter().any(|x| x.grade == Grade::Wrong) {
+            Grade::Wrong
+        } else if matched.iter().any(|x| x.grade == Grade::Almost) {
+            Grade::Almost
+        } else {
+            Grade::Correct
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Grade::Correct => write!(f, "correct"),
+            Grade::Almost => write!(f, "almost"),
+            Grade::Wrong => write!(f, "wrong"),
+        }
+    }
+}
+
+/// How forgiving the matcher is before it calls a word wrong.
+///
+/// - distance: max levenshtein distance on literals and long flags
+/// - short_alias: grade `-a` for `--all` (and the other way around) as a
+///   near miss. It is a guess by the first letter, so it is off by default.
+///   A declared alias like `-A/--all` accepts both spellings anyway.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tolerance {
+    pub distance: usize,
+    pub short_alias: bool,
+}
+
+impl Tolerance {
+    pub fn strict() -> Self {
+        Self {
+            distance: 0,
+            short_alias: false,
+        }
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            distance: 1,
+            short_alias: false,
+        }
+    }
+}
+
+/// One graded word of the answer.
+///
+/// - value: the word as the schema expects it
+/// - input: the word the user typed for it (if any)
+/// - grade: how close the input was to the value
+/// - note: why the input is wrong when we can tell, like a bad value type
+#[derive(Debug, PartialEq, Clone)]
+pub struct Matched {
+    pub value: String,
+    pub input: Option<String>,
+    pub grade: Grade,
+    pub note: Option<String>,
+}
+
+impl Matched {
+    fn new(value: String, input: Option<String>, grade: Grade) -> Self {
+        Self { value, input, grade, note: None }
+    }
+
+    /// Grades the value typed for a variable by its type.
+    fn variable(var: &CmdWord, word: &str) -> Self {
+        let note = match var {
+            CmdWord::Variable{kind, ..} => check_value(kind, word),
+            _ => None,
+        };
+        let grade = if note.is_some() { Grade::Wrong } else { Grade::Correct };
+        Self { note, ..Self::new(var.to_string(), Some(word.to_string()), grade) }
+    }
+
+    fn correct(value: String) -> Self {
+        Self::new(value.clone(), Some(value), Grade::Correct)
+    }
+
+    fn wrong(value: String, input: Option<String>) -> Self {
+        Self::new(value, input, Grade::Wrong)
+    }
+
+    /// The correction we show to the user for a near miss.
+    pub fn correction(&self) -> Option<String> {
+        match (&self.grade, &self.input) {
+            (Grade::Almost, Some(input)) => Some(format!("{input} -> {}", self.value)),
+            _ => None,
+        }
+    }
+}
+
+/// Checks the shape of a value typed for a variable and tells what
+/// is wrong with it.
+fn check_value(kind: &Variable, value: &str) -> Option<String> {
+    let valid = match kind {
+        Variable::String => true,
+        Variable::Int => value.parse::<i64>().is_ok(),
+        Variable::Path => is_path(value),
+        Variable::Url => is_url(value),
+        Variable::GitRef => is_git_ref(value),
+        Variable::Duration => is_duration(value),
+        Variable::Choice(values) => values.iter().any(|x| x == value),
+    };
+
+    if valid {
+        None
+    } else {
+        let expected = match kind {
+            Variable::String => "a string",
+            Variable::Int => "a number",
+            Variable::Path => "a path",
+            Variable::Url => "a url",
+            Variable::GitRef => "a git ref",
+            Variable::Duration => "a duration",
+            Variable::Choice(values) => return Some(format!("'{value}' is not one of {}", values.join(", "))),
+        };
+        Some(format!("'{value}' is not {expected}"))
+    }
+}
+
+// Almost anything can be a path, but not a flag or a url.
+fn is_path(value: &str) -> bool {
+    !value.is_empty() && !value.starts_with('-') && !value.contains("://")
+}
+
+// `scheme://host/...` or the scp like `git@host:path`.
+fn is_url(value: &str) -> bool {
+    if let Some((scheme, rest)) = value.split_once("://") {
+        !scheme.is_empty()
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+            && !rest.is_empty()
+    } else if let Some((user, rest)) = value.split_once('@') {
+        !user.is_empty() && rest.split_once(':').is_some_and(|(host, path)| !host.is_empty() && !path.is_empty())
+    } else {
+        false
+    }
+}
+
+// The rules of `git check-ref-format` plus revision suffixes like `HEAD~2`.
+fn is_git_ref(value: &str) -> bool {
+    let name = value.trim_end_matches(|c: char| c.is_ascii_digit() || c == '~' || c == '^');
+    let name = if name.is_empty() { value } else { name };
+
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.starts_with('/')
+        && !name.ends_with('/')
+        && !name.ends_with('.')
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && !name.chars().any(|c| c.is_whitespace() || c.is_control() || " :?*[\\".contains(c))
+}
+
+// `30`, `1.5s`, `500ms` or `1h30m`.
+fn is_duration(value: &str) -> bool {
+    if value.parse::<f64>().is_ok_and(|x| x >= 0.0) {
+        return true;
+    }
+
+    let mut rest = value;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        if digits == 0 || rest[..digits].parse::<f64>().is_err() {
+            return false;
+        }
+        rest = &rest[digits..];
+        let unit = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        if !matches!(&rest[..unit], "ms" | "s" | "m" | "h" | "d" | "w") {
+            return false;
+        }
+        rest = &rest[unit..];
+    }
+    !value.is_empty()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+fn grade_word(expected: &str, input: &str, tolerance: &Tolerance) -> Grade {
+    if expected == input {
+        Grade::Correct
+    } else if expected.chars().count() > tolerance.distance
+        && levenshtein(expected, input) <= tolerance.distance {
+        Grade::Almost
+    } else {
+        Grade::Wrong
+    }
+}
+
+/// Matches the literal and variable parts of a composite word against
+/// the typed word. Every variable part takes at least one char, and a
+/// value of the type of the variable.
+fn match_parts(parts: &[CmdWord], word: &str) -> bool {
+    match parts.first() {
+        None => word.is_empty(),
+        Some(CmdWord::Literal{value}) => {
+            word.strip_prefix(value.as_str()).is_some_and(|rest| match_parts(&parts[1..], rest))
+        },
+        Some(part) => {
+            word.char_indices()
+                .map(|(idx, _)| idx)
+                .chain(std::iter::once(word.len()))
+                .filter(|idx| *idx > 0)
+                .filter(|idx| match part {
+                    CmdWord::Variable{kind, ..} => check_value(kind, &word[..*idx]).is_none(),
+                    _ => true,
+                })
+                .any(|idx| match_parts(&parts[1..], &word[idx..]))
+        }
+    }
+}
+
+fn match_flag_input(input: &Option<CmdWord>, tokens: &[Token], token_idx: &mut usize, res: &mut Vec<Matched>) {
+    if let Some(input) = input.as_ref() {
+        match (input, tokens.get(*token_idx)) {
+            (CmdWord::Variable{..}, Some(Token::Str(word))) => {
+                res.push(Matched::variable(input, word));
+                *token_idx += 1;
+            },
+            (CmdWord::Composite{parts}, Some(Token::Str(word))) => {
+                let grade = if match_parts(parts, word) { Grade::Correct } else { Grade::Wrong };
+                res.push(Matched::new(input.to_string(), Some(word.clone()), grade));
+                *token_idx += 1;
+            },
+            _ => {
+                res.push(Matched::wrong(input.to_string(), None));
+            }
+        }
+    }
+}
+
+/// Short flags of the schema that take an input, like `-n <count>`.
+fn input_flags(ast: &[CmdWord]) -> Vec<char> {
+    ast.iter()
+        .flat_map(|word| match word {
+            CmdWord::FlagShort{value, input} if input.is_some() => vec![*value],
+            CmdWord::FlagAlias{short, input, ..} if input.is_some() => vec![*short],
+            CmdWord::Optional{word} | CmdWord::Repeated{word} => input_flags(std::slice::from_ref(word)),
+            CmdWord::BinaryOp{lhs, rhs, ..} => {
+                input_flags(&[lhs.as_ref().clone(), rhs.as_ref().clone()])
+            },
+            _ => vec![]
+        })
+        .collect()
+}
+
+/// The input lexer can not know which flags take a value, so `-n5` or
+/// `-lp<password>` come in as a combo. Once the schema tells us that the
+/// flag takes an input, the rest of the combo is its value.
+fn split_attached_values(tokens: &[Token], input_flags: &[char]) -> Vec<Token> {
+    let mut res = Vec::new();
+
+    for token in tokens {
+        match token {
+            Token::FlagCombo(chars) => {
+                if let Some(pos) = chars.iter().position(|c| input_flags.contains(c)) {
+                    for c in &chars[..=pos] {
+                        res.push(Token::FlagShort(*c));
+                    }
+                    if pos + 1 < chars.len() {
+                        res.push(Token::Str(chars[pos + 1..].iter().collect()));
+                    }
+                } else {
+                    res.push(token.clone());
+                }
+            },
+            _ => res.push(token.clone())
+        }
+    }
+
+    res
+}
+
+/// Short flags without input can be grouped and ordered in any way.
+/// Returns the flag chars and if they are required.
+fn short_flag_chars(word: &CmdWord) -> Option<(Vec<char>, bool)> {
+    match word {
+        CmdWord::FlagShort{value, input} if input.is_none() => Some((vec![*value], true)),
+        CmdWord::FlagCombo{values} => Some((values.clone(), true)),
+        CmdWord::Optional{word} => short_flag_chars(word).map(|(chars, _)| (chars, false)),
+        CmdWord::Repeated{word} => short_flag_chars(word),
+        _ => None
+    }
+}
+
+/// How bad a match is, used to pick the best of two ways to match.
+fn cost(matched: &[Matched]) -> usize {
+    matched.iter()
+        .map(|x| match x.grade {
+            Grade::Correct => 0,
+            Grade::Almost => 1,
+            Grade::Wrong => 2,
+        })
+        .sum()
+}
+
+fn token_flag_chars(token: &Token, input_flags: &[char]) -> Option<Vec<char>> {
+    match token {
+        Token::FlagShort(value) if !input_flags.contains(value) => Some(vec![*value]),
+        Token::FlagCombo(values) => Some(values.clone()),
+        _ => None
+    }
+}
+
+/// The answer split into the commands joined by connectors.
+fn token_stages(tokens: &[Token]) -> Vec<(&[Token], Option<&Connector>)> {
+    let mut res = Vec::new();
+    let mut start = 0;
+
+    for (idx, token) in tokens.iter().enumerate() {
+        if let Token::Connector(connector) = token {
+            res.push((&tokens[start..idx], Some(connector)));
+            start = idx + 1;
+        }
+    }
+    res.push((&tokens[start..], None));
+
+    res
+}
+
 //
 // schema: git add .
 // input: git add
@@ -73,94 +427,300 @@ use crate::parser::input_lexer::Token;
 ///         node: CmdWord
 ///     )
 /// ]
-pub fn match_schema(ast: &Vec<CmdWord>, tokens: &Vec<Token>, ast_idx: usize, token_idx: usize) -> Vec<(String, bool)> {
-    let mut ast_idx = ast_idx;
-    let mut token_idx = token_idx;
-    let mut res: Vec<(String, bool)> = Vec::new();
+///
+/// Pipelines and chains are compared command by command. The connectors
+/// between the commands have to match as well.
+pub fn match_schema(ast: &[CmdWord], tokens: &[Token], ast_idx: usize, token_idx: usize, tolerance: &Tolerance) -> Vec<Matched> {
+    let ast_stages = stages(&ast[ast_idx..]);
+    let token_stages = token_stages(&tokens[token_idx..]);
+    let mut res = Vec::new();
 
+    for idx in 0..ast_stages.len().max(token_stages.len()) {
+        let (words, connector) = ast_stages.get(idx).map_or((&[][..], None), |x| (x.words, x.connector));
+        let (typed, typed_connector) = token_stages.get(idx).copied().unwrap_or((&[], None));
 
-    while ast_idx < ast.len() && token_idx < tokens.len() {
-        let cmd = &ast[ast_idx];
-        let token = &tokens[token_idx];
+        res.extend(match_command(words, typed, tolerance));
 
-        match (cmd, token) {
-            (CmdWord::Literal{value}, Token::Str(word)) => {
-                res.push((cmd.to_string(), value == word));
-                ast_idx += 1;
-                token_idx += 1;
+        match (connector, typed_connector) {
+            (Some(connector), Some(typed)) => {
+                let grade = if connector == typed { Grade::Correct } else { Grade::Wrong };
+                res.push(Matched::new(connector.to_string(), Some(typed.to_string()), grade));
             },
-            (CmdWord::Variable{required,..}, token) => {
-                if let Token::Str(word) = token {
-                    res.push((word.clone(), true));
+            (Some(connector), None) => res.push(Matched::wrong(connector.to_string(), None)),
+            (None, Some(typed)) => res.push(Matched::wrong(typed.to_string(), Some(typed.to_string()))),
+            (None, None) => {}
+        }
+    }
+
+    res
+}
+
+/// Grades the answer against each of the accepted patterns of a quest and
+/// keeps the closest one. On a tie the pattern listed first wins.
+pub fn best_match(asts: &[Vec<CmdWord>], tokens: &[Token], tolerance: &Tolerance) -> (usize, Vec<Matched>) {
+    let rank = |matched: &[Matched]| {
+        let grade = match Grade::of(matched) {
+            Grade::Correct => 0,
+            Grade::Almost => 1,
+            Grade::Wrong => 2,
+        };
+        (grade, cost(matched))
+    };
+
+    asts.iter()
+        .map(|ast| match_schema(ast, tokens, 0, 0, tolerance))
+        .enumerate()
+        .min_by_key(|(_, matched)| rank(matched))
+        .expect("A quest needs at least one pattern")
+}
+
+/// The words of a command that are left to match. Optionals, repeats and
+/// alternatives put their inner words in front of the rest of the command.
+#[derive(Clone)]
+struct Words<'a> {
+    head: Vec<&'a CmdWord>,
+    rest: &'a [CmdWord],
+}
+
+impl<'a> Words<'a> {
+    fn get(&self, idx: usize) -> Option<&'a CmdWord> {
+        match self.head.get(idx) {
+            Some(word) => Some(*word),
+            None => self.rest.get(idx - self.head.len()),
+        }
+    }
+
+    /// The words after `idx`, with `words` put in front of them.
+    fn after(&self, idx: usize, words: &[&'a CmdWord]) -> Words<'a> {
+        let mut head = words.to_vec();
+        head.extend(self.head.iter().skip(idx));
+        let rest = &self.rest[idx.saturating_sub(self.head.len())..];
+        Words { head, rest }
+    }
+}
+
+/// Every way to match an optional, a repeat or an alternative matches the
+/// rest of the command again. The best match of the rest is kept for each
+/// place in the words and the answer, or long patterns take ages to grade.
+struct Matcher<'a> {
+    tokens: &'a [Token],
+    input_flags: &'a [char],
+    tolerance: &'a Tolerance,
+    memo: HashMap<(Vec<*const CmdWord>, *const CmdWord, usize, usize), Vec<Matched>>,
+}
+
+fn match_command(ast: &[CmdWord], tokens: &[Token], tolerance: &Tolerance) -> Vec<Matched> {
+    let input_flags = input_flags(ast);
+    let tokens = split_attached_values(tokens, &input_flags);
+    let mut matcher = Matcher { tokens: &tokens, input_flags: &input_flags, tolerance, memo: HashMap::new() };
+    matcher.match_words(Words { head: Vec::new(), rest: ast }, 0)
+}
+
+impl Matcher<'_> {
+    fn match_words(&mut self, words: Words, token_idx: usize) -> Vec<Matched> {
+        let key = (
+            words.head.iter().map(|x| *x as *const CmdWord).collect(),
+            words.rest.as_ptr(),
+            words.rest.len(),
+            token_idx,
+        );
+        if let Some(res) = self.memo.get(&key) {
+            return res.clone();
+        }
+        let res = self.match_uncached(words, token_idx);
+        self.memo.insert(key, res.clone());
+        res
+    }
+
+    /// Keeps the cheaper of two ways to match the rest.
+    fn best_of(&mut self, a: Words, b: Words, token_idx: usize) -> Vec<Matched> {
+        let a = self.match_words(a, token_idx);
+        let b = self.match_words(b, token_idx);
+        if cost(&b) < cost(&a) { b } else { a }
+    }
+
+    fn match_uncached(&mut self, words: Words, mut token_idx: usize) -> Vec<Matched> {
+        let (tokens, input_flags, tolerance) = (self.tokens, self.input_flags, self.tolerance);
+        let mut ast_idx = 0;
+        let mut res: Vec<Matched> = Vec::new();
+
+        while let (Some(cmd), Some(token)) = (words.get(ast_idx), tokens.get(token_idx)) {
+            match (cmd, token) {
+                (CmdWord::Literal{value}, Token::Str(word)) => {
+                    let grade = grade_word(value, word, tolerance);
+                    res.push(Matched::new(cmd.to_string(), Some(word.clone()), grade));
                     ast_idx += 1;
                     token_idx += 1;
-                } else {
-                    if *required {
-                        res.push((cmd.to_string(), false));
-                    }
+                },
+                (CmdWord::Composite{parts}, Token::Str(word)) => {
+                    let grade = if match_parts(parts, word) { Grade::Correct } else { Grade::Wrong };
+                    res.push(Matched::new(cmd.to_string(), Some(word.clone()), grade));
                     ast_idx += 1;
                     token_idx += 1;
-                }
-            },
-            (CmdWord::FlagShort{value, input}, Token::FlagShort(word)) => {
-                // TODO: Make an implementation for the flagShort only flag string
-                res.push((format!("-{value}"), value == word));
-                ast_idx += 1;
-                token_idx += 1;
-
-                if let Some(input) = input.as_ref() {
-                    match (input, tokens.get(token_idx)) {
-                        (CmdWord::Variable{..}, Some(Token::Str(_))) => {
-                            res.push((input.to_string(), true)) ;
-                            token_idx += 1;
-                        },
-                        _ => {
-                            res.push((input.to_string(), false));
+                },
+                (CmdWord::Variable{required,..}, token) => {
+                    if let Token::Str(word) = token {
+                        res.push(Matched::variable(cmd, word));
+                        ast_idx += 1;
+                        token_idx += 1;
+                    } else {
+                        if *required {
+                            res.push(Matched::wrong(cmd.to_string(), Some(token.to_string())));
                         }
+                        ast_idx += 1;
+                        token_idx += 1;
+                    }
+                },
+                (cmd, token) if short_flag_chars(cmd).is_some() && token_flag_chars(token, input_flags).is_some() => {
+                    // Compare the whole run of short flags as a set, so `-la`,
+                    // `-al` and `-l -a` are all the same answer.
+                    let mut typed = Vec::new();
+                    let mut typed_words = Vec::new();
+                    while let Some(chars) = tokens.get(token_idx).and_then(|x| token_flag_chars(x, input_flags)) {
+                        typed.extend(chars);
+                        typed_words.push(tokens[token_idx].to_string());
+                        token_idx += 1;
                     }
+
+                    let typed_input = typed_words.join(" ");
+                    let mut expected = Vec::new();
+                    while let Some((chars, required)) = words.get(ast_idx).and_then(short_flag_chars) {
+                        let is_typed = chars.iter().all(|c| typed.contains(c));
+                        if is_typed || required {
+                            let grade = if is_typed { Grade::Correct } else { Grade::Wrong };
+                            res.push(Matched::new(words.get(ast_idx).expect("A short flag").to_string(), Some(typed_input.clone()), grade));
+                        }
+                        expected.extend(chars);
+                        ast_idx += 1;
+                    }
+
+                    for c in typed.iter().filter(|c| !expected.contains(c)) {
+                        res.push(Matched::wrong(format!("-{c}"), Some(format!("-{c}"))));
+                    }
+                },
+                (CmdWord::FlagShort{value, input}, Token::FlagShort(word)) => {
+                    let grade = if value == word { Grade::Correct } else { Grade::Wrong };
+                    res.push(Matched::new(format!("-{value}"), Some(token.to_string()), grade));
+                    ast_idx += 1;
+                    token_idx += 1;
+                    match_flag_input(input, tokens, &mut token_idx, &mut res);
+                },
+                (CmdWord::FlagShort{value, input}, Token::FlagLong(word))
+                    if tolerance.short_alias && word.starts_with(*value) => {
+                    res.push(Matched::new(format!("-{value}"), Some(token.to_string()), Grade::Almost));
+                    ast_idx += 1;
+                    token_idx += 1;
+                    match_flag_input(input, tokens, &mut token_idx, &mut res);
+                },
+                (CmdWord::FlagLong{value, input}, Token::FlagLong(word)) => {
+                    let grade = grade_word(value, word, tolerance);
+                    res.push(Matched::new(format!("--{value}"), Some(token.to_string()), grade));
+                    ast_idx += 1;
+                    token_idx += 1;
+                    match_flag_input(input, tokens, &mut token_idx, &mut res);
+                },
+                (CmdWord::FlagLong{value, input}, Token::FlagShort(word))
+                    if tolerance.short_alias && value.starts_with(*word) => {
+                    res.push(Matched::new(format!("--{value}"), Some(token.to_string()), Grade::Almost));
+                    ast_idx += 1;
+                    token_idx += 1;
+                    match_flag_input(input, tokens, &mut token_idx, &mut res);
+                },
+                (CmdWord::FlagAlias{short, input, ..}, Token::FlagShort(word)) => {
+                    let grade = if short == word { Grade::Correct } else { Grade::Wrong };
+                    res.push(Matched::new(cmd.to_string(), Some(token.to_string()), grade));
+                    ast_idx += 1;
+                    token_idx += 1;
+                    match_flag_input(input, tokens, &mut token_idx, &mut res);
+                },
+                (CmdWord::FlagAlias{long, input, ..}, Token::FlagLong(word)) => {
+                    let grade = grade_word(long, word, tolerance);
+                    res.push(Matched::new(cmd.to_string(), Some(token.to_string()), grade));
+                    ast_idx += 1;
+                    token_idx += 1;
+                    match_flag_input(input, tokens, &mut token_idx, &mut res);
+                },
+                (CmdWord::Redirect{op, target}, Token::Redirect(typed)) => {
+                    let grade = if op == typed { Grade::Correct } else { Grade::Wrong };
+                    res.push(Matched::new(op.to_string(), Some(typed.to_string()), grade));
+                    ast_idx += 1;
+                    token_idx += 1;
+
+                    if let Some(target) = target.as_ref() {
+                        let end = (token_idx + 1).min(tokens.len());
+                        res.extend(match_command(std::slice::from_ref(target), &tokens[token_idx..end], tolerance));
+                        token_idx = end;
+                    }
+                },
+                (CmdWord::Substitution{words: sub}, Token::Substitution(typed)) => {
+                    res.extend(match_schema(sub, typed, 0, 0, tolerance));
+                    ast_idx += 1;
+                    token_idx += 1;
+                },
+                // Try to match the rest with and without the optional word
+                // and keep the better of the two.
+                (CmdWord::Optional{word}, _) => {
+                    let taken = words.after(ast_idx + 1, &[word.as_ref()]);
+                    let skipped = words.after(ast_idx + 1, &[]);
+                    res.extend(self.best_of(taken, skipped, token_idx));
+                    return res;
+                },
+                // Match the word once and see if the rest of the answer
+                // is better with another round of it.
+                (CmdWord::Repeated{word}, _) => {
+                    let once = words.after(ast_idx + 1, &[word.as_ref()]);
+                    let more = words.after(ast_idx + 1, &[word.as_ref(), cmd]);
+                    res.extend(self.best_of(once, more, token_idx));
+                    return res;
+                },
+                (CmdWord::BinaryOp{lhs, rhs, ..}, _) => {
+                    let left = words.after(ast_idx + 1, &[lhs.as_ref()]);
+                    let right = words.after(ast_idx + 1, &[rhs.as_ref()]);
+                    res.extend(self.best_of(left, right, token_idx));
+                    return res;
+                },
+                (CmdWord::Delimiter, Token::Delimiter) => {
+                    res.push(Matched::correct(cmd.to_string()));
+                    ast_idx += 1;
+                    token_idx += 1;
+                },
+                (cmd, token) => {
+                    tracing::info!("Cmd and token might be missing impl for {} and {}", cmd, token);
+                    res.push(Matched::wrong(cmd.to_string(), Some(token.to_string())));
+                    ast_idx += 1;
+                    token_idx += 1;
                 }
-            },
-            (CmdWord::FlagLong{value, ..}, Token::FlagLong(word)) => {
-                res.push((cmd.to_string(), value == word));
-                ast_idx += 1;
-                token_idx += 1;
-            },
-            (CmdWord::FlagCombo{values}, Token::FlagCombo(chars)) => {
-                res.push((cmd.to_string(), values == chars));
-                ast_idx += 1;
-                token_idx += 1;
-            },
-            (cmd, token) => {
-                res.push((cmd.to_string(), false));
-                println!("Cmd and token might be missing impl for {} and {}", cmd, token);
-                ast_idx += 1;
-                token_idx += 1;
             }
         }
-    }
 
-    while let Some(cmd) = ast.get(ast_idx) {
-        match cmd {
-            CmdWord::Variable { required, .. } => {
-                res.push((cmd.to_string(), *required));
-            },
-            _ => {
-                res.push((cmd.to_string(), false));
+        while let Some(cmd) = words.get(ast_idx) {
+            match cmd {
+                CmdWord::Variable { required, kind, .. } => {
+                    let grade = if *required { Grade::Wrong } else { Grade::Correct };
+                    let mut matched = Matched::new(cmd.to_string(), None, grade);
+                    if let (true, Variable::Choice(values)) = (required, kind) {
+                        matched.note = Some(format!("expected one of {}", values.join(", ")));
+                    }
+                    res.push(matched);
+                },
+                CmdWord::Optional { .. } => {},
+                _ => {
+                    res.push(Matched::wrong(cmd.to_string(), None));
+                }
             }
+            ast_idx += 1;
         }
-        ast_idx += 1;
-    }
 
-    while let Some(token) = tokens.get(token_idx) {
-        res.push((token.to_string(), false));
-        token_idx += 1;
-    }
+        while let Some(token) = tokens.get(token_idx) {
+            res.push(Matched::wrong(token.to_string(), Some(token.to_string())));
+            token_idx += 1;
+        }
 
-    return res 
+        res
+    }
 }
 
-
-
+#[cfg(test)]
 mod tests {
     use super::*;
     use crate::parser::parser::CmdParser;
@@ -175,12 +735,12 @@ mod tests {
 
         for (cmd, s) in tests {
             let ast = CmdParser::compile(cmd);
-            let input = InputCmdLexer::compile(s);
-            let matcher = match_schema(&ast, &input, 0, 0);
+            let input = InputCmdLexer::compile(s).unwrap();
+            let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::strict());
 
-            for (val, correctness) in matcher {
-                assert_eq!(val, s);
-                assert_eq!(correctness, val == s);
+            for item in matcher {
+                assert_eq!(item.value, s);
+                assert_eq!(item.grade == Grade::Correct, item.value == s);
             }
         }
     }
@@ -188,69 +748,69 @@ mod tests {
     #[test]
     fn match_multiple_items() {
         let val = "git add";
-        let ast = CmdParser::compile(&val);
-        let input = InputCmdLexer::compile(&val);
-        let matcher = match_schema(&ast, &input, 0, 0);
+        let ast = CmdParser::compile(val);
+        let input = InputCmdLexer::compile(val).unwrap();
+        let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::strict());
         let splits = val.split_whitespace().collect::<Vec<_>>();
 
-        for (i, (cmd, s)) in matcher.iter().enumerate() {
+        for (i, item) in matcher.iter().enumerate() {
             let split = splits[i];
-           assert_eq!(cmd, split);
-           assert_eq!(*s, cmd.as_str() == split);
+           assert_eq!(item.value, split);
+           assert_eq!(item.grade == Grade::Correct, item.value.as_str() == split);
         }
     }
 
     #[test]
     fn match_short_flag() {
         let val = "git -f";
-        let ast = CmdParser::compile(&val);
-        let input = InputCmdLexer::compile(&val);
-        let matcher = match_schema(&ast, &input, 0, 0);
+        let ast = CmdParser::compile(val);
+        let input = InputCmdLexer::compile(val).unwrap();
+        let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::strict());
         let splits = val.split_whitespace().collect::<Vec<_>>();
 
-        for (i, (cmd, s)) in matcher.iter().enumerate() {
+        for (i, item) in matcher.iter().enumerate() {
             let split = splits[i];
-           assert_eq!(cmd, split);
-           assert_eq!(*s, cmd.as_str() == split);
+           assert_eq!(item.value, split);
+           assert_eq!(item.grade == Grade::Correct, item.value.as_str() == split);
         }
     }
 
     #[test]
     fn match_long_flag() {
         let val = "git --depht";
-        let ast = CmdParser::compile(&val);
-        let input = InputCmdLexer::compile(&val);
-        let matcher = match_schema(&ast, &input, 0, 0);
+        let ast = CmdParser::compile(val);
+        let input = InputCmdLexer::compile(val).unwrap();
+        let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::strict());
         let splits = val.split_whitespace().collect::<Vec<_>>();
 
-        for (i, (cmd, s)) in matcher.iter().enumerate() {
+        for (i, item) in matcher.iter().enumerate() {
             let split = splits[i];
-           assert_eq!(cmd, split);
-           assert_eq!(*s, cmd.as_str() == split);
+           assert_eq!(item.value, split);
+           assert_eq!(item.grade == Grade::Correct, item.value.as_str() == split);
         }
     }
 
     #[test]
     fn match_combo_flag() {
         let val = "git -la";
-        let ast = CmdParser::compile(&val);
-        let input = InputCmdLexer::compile(&val);
-        let matcher = match_schema(&ast, &input, 0, 0);
+        let ast = CmdParser::compile(val);
+        let input = InputCmdLexer::compile(val).unwrap();
+        let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::strict());
         let splits = val.split_whitespace().collect::<Vec<_>>();
 
-        for (i, (cmd, s)) in matcher.iter().enumerate() {
+        for (i, item) in matcher.iter().enumerate() {
             let split = splits[i];
-           assert_eq!(cmd, split);
-           assert_eq!(*s, cmd.as_str() == split);
+           assert_eq!(item.value, split);
+           assert_eq!(item.grade == Grade::Correct, item.value.as_str() == split);
         }
     }
 
     // #[test]
     // fn match_flag_input() {
     //     let val = "git -l <path>";
-    //     let ast = CmdParser::compile(&val);
+    //     let ast = CmdParser::compile(val);
     //     let input = InputCmdLexer::compile("git -l path");
-    //     let matcher = match_schema(&ast, &input, 0, 0);
+    //     let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::strict());
     //     let splits = val.split_whitespace().collect::<Vec<_>>();
     //
     //     for (i, (cmd, s)) in matcher.iter().enumerate() {
@@ -259,4 +819,284 @@ mod tests {
     //        assert_eq!(*s, cmd.as_str() == split);
     //     }
     // }
+
+    fn grades(pattern: &str, answer: &str) -> Vec<Grade> {
+        let ast = CmdParser::compile(pattern);
+        let input = InputCmdLexer::compile(answer).unwrap();
+        match_schema(&ast, &input, 0, 0, &Tolerance::default())
+            .iter()
+            .map(|x| x.grade)
+            .collect()
+    }
+
+    #[test]
+    fn typo_in_literal_is_almost() {
+        assert_eq!(grades("git commit", "git comit"), vec![Grade::Correct, Grade::Almost]);
+        assert_eq!(grades("git commit", "git cmt"), vec![Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn typo_in_long_flag_is_almost() {
+        assert_eq!(grades("git clone --depth", "git clone --dept"), vec![Grade::Correct, Grade::Correct, Grade::Almost]);
+    }
+
+    #[test]
+    fn short_alias_of_long_flag_is_almost() {
+        let tolerance = Tolerance { short_alias: true, ..Tolerance::default() };
+        let grades = |cmd: &str, s: &str| -> Vec<Grade> {
+            let input = InputCmdLexer::compile(s).unwrap();
+            match_schema(&CmdParser::compile(cmd), &input, 0, 0, &tolerance).iter().map(|x| x.grade).collect()
+        };
+        assert_eq!(grades("git add --all", "git add -a"), vec![Grade::Correct, Grade::Correct, Grade::Almost]);
+        assert_eq!(grades("git add --all", "git add -b"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn no_short_alias_guess_by_default() {
+        assert_eq!(grades("git push -f", "git push --follow-tags"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("git add --all", "git add -a"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn strict_tolerance_has_no_near_miss() {
+        let ast = CmdParser::compile("git commit");
+        let input = InputCmdLexer::compile("git comit").unwrap();
+        let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::strict());
+        assert_eq!(Grade::of(&matcher), Grade::Wrong);
+    }
+
+    #[test]
+    fn almost_shows_correction() {
+        let ast = CmdParser::compile("git commit");
+        let input = InputCmdLexer::compile("git comit").unwrap();
+        let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::default());
+        assert_eq!(Grade::of(&matcher), Grade::Almost);
+        assert_eq!(matcher[1].correction(), Some("comit -> commit".to_string()));
+    }
+
+    #[test]
+    fn flag_alias_accepts_both_spellings() {
+        assert_eq!(grades("git add -A/--all", "git add -A"), vec![Grade::Correct, Grade::Correct, Grade::Correct]);
+        assert_eq!(grades("git add -A/--all", "git add --all"), vec![Grade::Correct, Grade::Correct, Grade::Correct]);
+        assert_eq!(grades("git add -A/--all", "git add -a"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn flag_alias_shows_canonical_form() {
+        let ast = CmdParser::compile("git add --all/-A");
+        let input = InputCmdLexer::compile("git add --al").unwrap();
+        let matcher = match_schema(&ast, &input, 0, 0, &Tolerance::default());
+        assert_eq!(matcher[2].value, "-A/--all");
+        assert_eq!(matcher[2].grade, Grade::Almost);
+    }
+
+    #[test]
+    fn combo_flags_in_any_order() {
+        assert_eq!(grades("ls -al", "ls -la"), vec![Grade::Correct, Grade::Correct]);
+        assert_eq!(grades("ls -la", "ls -l -a"), vec![Grade::Correct, Grade::Correct]);
+        assert_eq!(grades("ls -l -a", "ls -al"), vec![Grade::Correct, Grade::Correct, Grade::Correct]);
+        assert_eq!(grades("ls -la", "ls -l"), vec![Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("ls -la", "ls -lax"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn short_flag_with_attached_value() {
+        assert_eq!(grades("head -n <count>", "head -n5"), vec![Grade::Correct, Grade::Correct, Grade::Correct]);
+        assert_eq!(grades("head -n <count>", "head -n 5"), vec![Grade::Correct, Grade::Correct, Grade::Correct]);
+        assert_eq!(grades("mysql -u <user> -p<password>", "mysql -u root -psecret"), vec![
+            Grade::Correct, Grade::Correct, Grade::Correct, Grade::Correct, Grade::Correct
+        ]);
+        assert_eq!(grades("tail -f -n <count>", "tail -fn5"), vec![
+            Grade::Correct, Grade::Correct, Grade::Correct, Grade::Correct
+        ]);
+    }
+
+    #[test]
+    fn long_flag_input_with_or_without_equal() {
+        let all_correct = vec![Grade::Correct; 5];
+        assert_eq!(grades("git clone --depth <depth> <repo>", "git clone --depth=1 repo"), all_correct);
+        assert_eq!(grades("git clone --depth <depth> <repo>", "git clone --depth 1 repo"), all_correct);
+        assert_eq!(grades("git clone --depth=<depth> <repo>", "git clone --depth 1 repo"), all_correct);
+    }
+
+    #[test]
+    fn arguments_after_delimiter_are_positional() {
+        assert_eq!(grades("cargo run -- <arg>", "cargo run -- --release"), vec![Grade::Correct; 4]);
+        assert_eq!(grades("cargo run -- <arg>", "cargo run --release"), vec![
+            Grade::Correct, Grade::Correct, Grade::Wrong, Grade::Wrong
+        ]);
+    }
+
+    #[test]
+    fn pipeline_compares_each_stage() {
+        assert_eq!(grades("ps aux | grep <name>", "ps aux | grep ghi"), vec![Grade::Correct; 5]);
+        assert_eq!(grades("ps aux | grep <name>", "ps aux | gerp ghi"), vec![
+            Grade::Correct, Grade::Correct, Grade::Correct, Grade::Wrong, Grade::Correct
+        ]);
+        assert_eq!(grades("ps aux | grep <name>", "ps aux && grep ghi"), vec![
+            Grade::Correct, Grade::Correct, Grade::Wrong, Grade::Correct, Grade::Correct
+        ]);
+        assert_eq!(grades("ps aux | grep <name>", "ps aux"), vec![
+            Grade::Correct, Grade::Correct, Grade::Wrong, Grade::Wrong, Grade::Wrong
+        ]);
+    }
+
+    #[test]
+    fn redirect_with_target() {
+        assert_eq!(grades("ls > <file>", "ls > out.txt"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("ls > <file>", "ls >> out.txt"), vec![Grade::Correct, Grade::Wrong, Grade::Correct]);
+        assert_eq!(grades("ls > <file>", "ls >"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn command_substitution() {
+        assert_eq!(grades("kill $(pgrep <name>)", "kill $(pgrep app)"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("kill $(pgrep <name>)", "kill $(pgrp app)"), vec![
+            Grade::Correct, Grade::Almost, Grade::Correct
+        ]);
+    }
+
+    #[test]
+    fn composite_words() {
+        assert_eq!(grades("git reset HEAD~<n>", "git reset HEAD~2"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git reset HEAD~<n>", "git reset HEAD~"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("git reset HEAD~<n>", "git reset HEAD^2"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("git reset HEAD~<n:int>", "git reset HEAD~3"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git reset HEAD~<n:int>", "git reset HEAD~abc"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("git diff <a:int>..<b:int>", "git diff 1..20"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("cd \"~\"<username>", "cd ~sam"), vec![Grade::Correct; 2]);
+        assert_eq!(grades("git push origin/<branch>", "git push origin/main"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("touch <file>.txt", "touch notes.txt"), vec![Grade::Correct; 2]);
+        assert_eq!(grades("touch <file>.txt", "touch notes.md"), vec![Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("tar -f <name>.tar", "tar -f backup.tar"), vec![Grade::Correct; 3]);
+    }
+
+    #[test]
+    fn optional_literal() {
+        assert_eq!(grades("cd [\"~\"]", "cd"), vec![Grade::Correct]);
+        assert_eq!(grades("cd [\"~\"]", "cd ~"), vec![Grade::Correct; 2]);
+        assert_eq!(grades("cd [\"~\"]", "cd /"), vec![Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn optional_flags() {
+        assert_eq!(grades("git reset [-q] <commit>", "git reset abc"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git reset [-q] <commit>", "git reset -q abc"), vec![Grade::Correct; 4]);
+        assert_eq!(grades("ls [-l] [-a] -h", "ls -ha"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("tmux new [-s <name>]", "tmux new -s work"), vec![Grade::Correct; 4]);
+        assert_eq!(grades("tmux new [-s <name>]", "tmux new"), vec![Grade::Correct; 2]);
+    }
+
+    #[test]
+    fn alternatives() {
+        assert_eq!(grades("git reset [--hard | --soft]", "git reset --soft"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git reset [--hard | --soft]", "git reset --hard"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git reset [--hard | --soft]", "git reset"), vec![Grade::Correct; 2]);
+        assert_eq!(grades("git reset [--hard | --soft]", "git reset --mixed"), vec![
+            Grade::Correct, Grade::Correct, Grade::Wrong
+        ]);
+    }
+
+    #[test]
+    fn quoted_literals() {
+        assert_eq!(grades("cd \"..\"", "cd .."), vec![Grade::Correct; 2]);
+        assert_eq!(grades("cd ..", "cd .."), vec![Grade::Correct; 2]);
+        assert_eq!(grades("echo \"a | b\"", "echo 'a | b'"), vec![Grade::Correct; 2]);
+    }
+
+    #[test]
+    fn typed_variables() {
+        assert_eq!(grades("head -n <lines:int>", "head -n 20"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("head -n <lines:int>", "head -n abc"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("git clone <url:url>", "git clone https://github.com/a/b.git"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git clone <url:url>", "git clone git@github.com:a/b.git"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git clone <url:url>", "git clone github"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("git switch <branch:ref>", "git switch feat/login"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git switch <branch:ref>", "git switch a..b"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        // Without a type any value goes, like before the types
+        assert_eq!(grades("git clone <url>", "git clone github.com/a/b"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git log -n <n>", "git log -n abc"), vec![Grade::Correct; 4]);
+        assert_eq!(grades("git reset <commit:ref>", "git reset HEAD~2"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("sleep <time:duration>", "sleep 1h30m"), vec![Grade::Correct; 2]);
+        assert_eq!(grades("sleep <time:duration>", "sleep 1.5"), vec![Grade::Correct; 2]);
+        assert_eq!(grades("sleep <time:duration>", "sleep soon"), vec![Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("cat <path:file>", "cat ./src/main.rs"), vec![Grade::Correct; 2]);
+    }
+
+    #[test]
+    fn wrong_value_type_has_note() {
+        let ast = CmdParser::compile("head -n <lines:int>");
+        let tokens = InputCmdLexer::compile("head -n abc").unwrap();
+        let res = match_schema(&ast, &tokens, 0, 0, &Tolerance::default());
+
+        assert_eq!(res[2].note, Some("'abc' is not a number".to_string()));
+        assert_eq!(res[0].note, None);
+    }
+
+    #[test]
+    fn choice_variables() {
+        assert_eq!(grades("git reset <mode:soft|mixed|hard>", "git reset mixed"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("ls --color <when:auto|never>", "ls --color never"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("ls --color <when:auto|never>", "ls --color always"), vec![
+            Grade::Correct, Grade::Correct, Grade::Wrong
+        ]);
+
+        let ast = CmdParser::compile("git reset <mode:soft|mixed|hard>");
+        let tokens = InputCmdLexer::compile("git reset medium").unwrap();
+        let res = match_schema(&ast, &tokens, 0, 0, &Tolerance::default());
+        assert_eq!(res[2].note, Some("'medium' is not one of soft, mixed, hard".to_string()));
+
+        let tokens = InputCmdLexer::compile("git reset").unwrap();
+        let res = match_schema(&ast, &tokens, 0, 0, &Tolerance::default());
+        assert_eq!(res[2].note, Some("expected one of soft, mixed, hard".to_string()));
+    }
+
+    #[test]
+    fn repeated_words() {
+        assert_eq!(grades("git add <path>...", "git add a.txt"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("git add <path>...", "git add a.txt b.txt c.txt"), vec![Grade::Correct; 5]);
+        assert_eq!(grades("git add <path>...", "git add"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+        assert_eq!(grades("git add [<path>...]", "git add"), vec![Grade::Correct; 2]);
+        assert_eq!(grades("cp <src>... <dest>", "cp a b c"), vec![Grade::Correct; 4]);
+        assert_eq!(grades("ssh -v... <host>", "ssh -vvv box"), vec![Grade::Correct; 3]);
+        assert_eq!(grades("grep -e <pattern>... <file>", "grep -e a -e b f"), vec![Grade::Correct; 6]);
+    }
+
+    #[test]
+    fn missing_required_variable_is_wrong() {
+        assert_eq!(grades("git add <path>", "git add"), vec![Grade::Correct, Grade::Correct, Grade::Wrong]);
+    }
+
+    #[test]
+    fn best_of_patterns() {
+        let asts = vec![
+            CmdParser::compile("git switch -c <branch>"),
+            CmdParser::compile("git checkout -b <branch>"),
+        ];
+
+        let tokens = InputCmdLexer::compile("git checkout -b feat").unwrap();
+        let (idx, res) = best_match(&asts, &tokens, &Tolerance::default());
+        assert_eq!(idx, 1);
+        assert_eq!(Grade::of(&res), Grade::Correct);
+
+        let tokens = InputCmdLexer::compile("git switch -c feat").unwrap();
+        assert_eq!(best_match(&asts, &tokens, &Tolerance::default()).0, 0);
+
+        let tokens = InputCmdLexer::compile("git chekout -b feat").unwrap();
+        let (idx, res) = best_match(&asts, &tokens, &Tolerance::default());
+        assert_eq!(idx, 1);
+        assert_eq!(Grade::of(&res), Grade::Almost);
+    }
+
+    #[test]
+    fn many_optionals_grade_quickly() {
+        let pattern = format!("cmd {}", vec!["[\"x\"]"; 20].join(" "));
+        let start = std::time::Instant::now();
+        assert_eq!(grades(&pattern, &format!("cmd {}", vec!["x"; 20].join(" "))), vec![Grade::Correct; 21]);
+        assert_eq!(grades(&pattern, "cmd x x y"), vec![Grade::Correct, Grade::Correct, Grade::Correct, Grade::Wrong]);
+
+        let pattern = format!("cmd {}", ('a'..='t').map(|x| format!("[--opt{x}]")).collect::<Vec<_>>().join(" "));
+        assert_eq!(grades(&pattern, "cmd --optc --optr"), vec![Grade::Correct; 3]);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1), "took {:?}", start.elapsed());
+    }
 }
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use super::parser::{to_pattern, CmdWord, Variable};
+
+// Values that read well for the common variable names.
+fn named_examples(name: &str) -> &'static [&'static str] {
+    match name {
+        "branch" => &["feature/login", "fix/typo", "release/1.2"],
+        "commit" => &["HEAD~2", "a1b2c3d", "main"],
+        "tag" => &["v1.2.0", "v2.0.0-rc1"],
+        "remote" => &["origin", "upstream"],
+        "message" | "msg" => &["Fix the login form", "Add a readme"],
+        "host" => &["example.com", "build-server"],
+        "user" => &["alice", "bob"],
+        "pattern" => &["TODO", "error"],
+        _ => &[],
+    }
+}
+
+fn kind_examples(kind: &Variable) -> Vec<String> {
+    let values: &[&str] = match kind {
+        Variable::String => &["notes", "demo", "backup"],
+        Variable::Int => return vec![rand::thread_rng().gen_range(1..=10).to_string()],
+        Variable::Path => &["src/main.rs", "README.md", "docs/notes.txt"],
+        Variable::Url => &["https://github.com/user/repo.git", "https://example.com/archive.tar.gz"],
+        Variable::GitRef => &["main", "feature/login", "v1.2.0"],
+        Variable::Duration => &["30s", "5m", "1h"],
+        Variable::Choice(values) => return values.clone(),
+    };
+    values.iter().map(|x| x.to_string()).collect()
+}
+
+/// Fills the variables of patterns with concrete values.
+///
+/// - examples: the values of the `[examples]` config, by variable name
+/// - values: the values picked so far, so a variable that is in more
+///   patterns of the same quest gets the same value in all of them
+pub struct Instance<'a> {
+    examples: &'a HashMap<String, Vec<String>>,
+    pub values: Vec<(String, String)>,
+}
+
+impl<'a> Instance<'a> {
+    pub fn new(examples: &'a HashMap<String, Vec<String>>) -> Self {
+        Self { examples, values: Vec::new() }
+    }
+
+    fn value(&mut self, name: &str, kind: &Variable) -> String {
+        if let Some((_, value)) = self.values.iter().find(|(x, _)| x == name) {
+            return value.clone();
+        }
+
+        let candidates = match self.examples.get(name) {
+            Some(values) if !values.is_empty() => values.clone(),
+            _ if !named_examples(name).is_empty() && !matches!(kind, Variable::Choice(_)) => {
+                named_examples(name).iter().map(|x| x.to_string()).collect()
+            },
+            // A name like `<url>` reads better with a value of the type it hints at
+            _ if *kind == Variable::String => kind_examples(&Variable::suggest(name).unwrap_or(Variable::String)),
+            _ => kind_examples(kind),
+        };
+        let value = candidates.choose(&mut rand::thread_rng()).cloned().unwrap_or_else(|| name.to_string());
+        self.values.push((name.to_string(), value.clone()));
+        value
+    }
+
+    /// The pattern with its required variables set to one value each. The
+    /// value is kept as a choice of one, so the answer has to use exactly
+    /// it but is still graded as a variable.
+    pub fn fill(&mut self, words: &[CmdWord]) -> Vec<CmdWord> {
+        words.iter().map(|x| self.fill_word(x)).collect()
+    }
+
+    fn fill_input(&mut self, input: &Option<CmdWord>) -> Box<Option<CmdWord>> {
+        Box::new(input.as_ref().map(|x| self.fill_word(x)))
+    }
+
+    fn fill_word(&mut self, word: &CmdWord) -> CmdWord {
+        match word {
+            CmdWord::Variable { name, kind, required: true } => {
+                let value = self.value(name, kind);
+                CmdWord::Variable { name: name.clone(), kind: Variable::Choice(vec![value]), required: true }
+            },
+            CmdWord::FlagShort { value, input } => CmdWord::FlagShort { value: *value, input: self.fill_input(input) },
+            CmdWord::FlagLong { value, input } => CmdWord::FlagLong { value: value.clone(), input: self.fill_input(input) },
+            CmdWord::FlagAlias { short, long, input } => {
+                CmdWord::FlagAlias { short: *short, long: long.clone(), input: self.fill_input(input) }
+            },
+            CmdWord::Redirect { op, target } => CmdWord::Redirect { op: op.clone(), target: self.fill_input(target) },
+            CmdWord::Substitution { words } => CmdWord::Substitution { words: self.fill(words) },
+            // The parts of a word are matched by their text, so the value becomes a literal
+            CmdWord::Composite { parts } => CmdWord::Composite {
+                parts: parts.iter()
+                    .map(|part| match part {
+                        CmdWord::Variable { name, kind, .. } => CmdWord::Literal { value: self.value(name, kind) },
+                        part => part.clone(),
+                    })
+                    .collect(),
+            },
+            // One example value is enough to practice a repeated one
+            CmdWord::Repeated { word: inner } if matches!(**inner, CmdWord::Variable { .. }) => self.fill_word(inner),
+            CmdWord::BinaryOp { op, lhs, rhs } => CmdWord::BinaryOp {
+                op: op.clone(),
+                lhs: Box::new(self.fill_word(lhs)),
+                rhs: Box::new(self.fill_word(rhs)),
+            },
+            word => word.clone(),
+        }
+    }
+
+    /// The values picked for the question, like `branch: feature/login`.
+    pub fn describe(&self) -> String {
+        self.values.iter().map(|(name, value)| format!("{name}: {value}")).collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn show_word(word: &CmdWord) -> CmdWord {
+    let show_input = |input: &Option<CmdWord>| Box::new(input.as_ref().map(show_word));
+    match word {
+        CmdWord::Variable { kind: Variable::Choice(values), required: true, .. } if values.len() == 1 => {
+            CmdWord::Literal { value: values[0].clone() }
+        },
+        CmdWord::FlagShort { value, input } => CmdWord::FlagShort { value: *value, input: show_input(input) },
+        CmdWord::FlagLong { value, input } => CmdWord::FlagLong { value: value.clone(), input: show_input(input) },
+        CmdWord::FlagAlias { short, long, input } => {
+            CmdWord::FlagAlias { short: *short, long: long.clone(), input: show_input(input) }
+        },
+        CmdWord::Redirect { op, target } => CmdWord::Redirect { op: op.clone(), target: show_input(target) },
+        CmdWord::Substitution { words } => CmdWord::Substitution { words: words.iter().map(show_word).collect() },
+        CmdWord::BinaryOp { op, lhs, rhs } => CmdWord::BinaryOp {
+            op: op.clone(),
+            lhs: Box::new(show_word(lhs)),
+            rhs: Box::new(show_word(rhs)),
+        },
+        word => word.clone(),
+    }
+}
+
+/// The filled pattern with its values as literals, to print it like the
+/// command one would type.
+pub fn example_words(words: &[CmdWord]) -> Vec<CmdWord> {
+    words.iter().map(show_word).collect()
+}
+
+/// The filled pattern as the command one would type, e.g.
+/// `git switch -c feature/login`.
+pub fn to_example(words: &[CmdWord]) -> String {
+    // Values with spaces are printed quoted, like they are typed in the shell
+    to_pattern(&example_words(words))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::compare::{match_schema, Grade, Tolerance};
+    use crate::parser::input_lexer::InputCmdLexer;
+    use crate::parser::parser::CmdParser;
+
+    fn grade(ast: &[CmdWord], input: &str) -> Grade {
+        let tokens = InputCmdLexer::compile(input).unwrap();
+        Grade::of(&match_schema(ast, &tokens, 0, 0, &Tolerance::default()))
+    }
+
+    #[test]
+    fn fill_from_config() {
+        let examples = HashMap::from([("branch".to_string(), vec!["feature/login".to_string()])]);
+        let mut instance = Instance::new(&examples);
+        let ast = instance.fill(&CmdParser::compile("git switch -c <branch>"));
+
+        assert_eq!(instance.describe(), "branch: feature/login");
+        assert_eq!(to_example(&ast), "git switch -c feature/login");
+        assert_eq!(grade(&ast, "git switch -c feature/login"), Grade::Correct);
+        assert_eq!(grade(&ast, "git switch -c main"), Grade::Wrong);
+    }
+
+    #[test]
+    fn same_value_in_all_patterns() {
+        let examples = HashMap::new();
+        let mut instance = Instance::new(&examples);
+        let first = instance.fill(&CmdParser::compile("git switch -c <branch>"));
+        let second = instance.fill(&CmdParser::compile("git checkout -b <branch>"));
+
+        assert_eq!(instance.values.len(), 1);
+        let value = &instance.values[0].1;
+        assert_eq!(to_example(&first), format!("git switch -c {value}"));
+        assert_eq!(to_example(&second), format!("git checkout -b {value}"));
+    }
+
+    #[test]
+    fn fill_by_kind() {
+        let examples = HashMap::new();
+        let mut instance = Instance::new(&examples);
+        let ast = instance.fill(&CmdParser::compile("git log -n <n> HEAD~<count> [<path>]"));
+        let n = &instance.values[0].1;
+        let count = &instance.values[1].1;
+
+        assert!(n.parse::<i64>().is_ok());
+        assert_eq!(grade(&ast, &format!("git log -n {n} HEAD~{count}")), Grade::Correct);
+        assert_eq!(grade(&ast, &format!("git log -n {n} HEAD~{count} a.txt")), Grade::Correct);
+    }
+
+    #[test]
+    fn quoted_values() {
+        let examples = HashMap::from([("message".to_string(), vec!["Fix the login form".to_string()])]);
+        let mut instance = Instance::new(&examples);
+        let ast = instance.fill(&CmdParser::compile("git commit -m <message>"));
+
+        assert_eq!(to_example(&ast), "git commit -m \"Fix the login form\"");
+        assert_eq!(grade(&ast, "git commit -m \"Fix the login form\""), Grade::Correct);
+    }
+
+    #[test]
+    fn fill_repeated() {
+        let examples = HashMap::from([("path".to_string(), vec!["a.txt".to_string()])]);
+        let mut instance = Instance::new(&examples);
+        let ast = instance.fill(&CmdParser::compile("git add <path>..."));
+
+        assert_eq!(to_example(&ast), "git add a.txt");
+    }
+}
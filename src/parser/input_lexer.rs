@@ -1,11 +1,12 @@
 use std::fmt;
+use super::parser::{Connector, Redirect};
 
 ///
-/// TODO: implement a delimiter "--"
+/// The delimiter "--" ends the options. Everything after it is a string.
 /// ---
 /// example: cargo run -- arg
 ///
-/// TODO: implement an explicit flag input
+/// An explicit flag input is split into the flag and its value.
 /// ---
 /// example: command --option=23
 #[derive(Debug, PartialEq, Clone)]
@@ -13,8 +14,11 @@ pub enum Token {
     FlagShort(char),
     FlagCombo(Vec<char>),
     FlagLong(String),
+    Delimiter,
+    Connector(Connector),
+    Redirect(Redirect),
+    Substitution(Vec<Token>),
     Str(String),
-    Int(usize) // TODO: not implemented yet
 }
 
 impl fmt::Display for Token {
@@ -26,137 +30,345 @@ impl fmt::Display for Token {
                 write!(f, "-{flag}")
             },
             Token::FlagLong(val) => write!(f, "--{val}"),
+            Token::Delimiter => write!(f, "--"),
+            Token::Connector(val) => write!(f, "{val}"),
+            Token::Redirect(val) => write!(f, "{val}"),
+            Token::Substitution(tokens) => {
+                let words: Vec<String> = tokens.iter().map(|x| x.to_string()).collect();
+                write!(f, "$({})", words.join(" "))
+            },
             Token::Str(val) => write!(f, "{val}"),
-            Token::Int(val) => write!(f, "{val}")
         }
     }
 }
 
-pub struct InputCmdLexer<'a > {
-    input: &'a str, 
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// A word of the answer as the shell would see it after quote removal.
+///
+/// - value: the text of the word without quotes and escapes
+/// - quoted: the word starts with a quote or an escape, so `"-f"` is
+///   a string and not a flag.
+struct Word {
+    value: String,
+    quoted: bool,
+}
+
+/// Splits the answer into shell words the way a POSIX shell does
+/// (without any expansion) and turns them into tokens.
+///
+/// - whitespace is space, tab and new line
+/// - `\` escapes the next char outside of quotes
+/// - '...' is taken as is
+/// - "..." only escapes `$`, `` ` ``, `"`, `\` and new line
+/// - $'...' understands the ANSI C escapes like `\n`
+/// - adjacent fragments form one word: `"a"'b'c` is `abc`
+/// - variables like `$HOME` are kept as they are written
+/// - `|`, `&&`, `||` and `;` join commands
+/// - `>`, `>>`, `<`, `2>` and `2>&1` redirect
+/// - a whole word `$(...)` is a command substitution
+pub struct InputCmdLexer {
+    input: Vec<char>,
     position: usize,
-    read_position: usize,
-    ch: Option<char>
+    // the value of `--flag=value` waiting to be returned after the flag
+    pending: Option<Token>,
+    // we are past the `--` delimiter
+    positional: bool,
 }
 
-impl<'a> InputCmdLexer<'a> {
-    pub fn compile(input: &'a str) -> Vec<Token> {
+impl InputCmdLexer {
+    pub fn compile(input: &str) -> Result<Vec<Token>, LexError> {
         let mut tokens = Vec::new();
         let mut lexer = Self {
-            input,
+            input: input.chars().collect(),
             position: 0,
-            read_position: 0,
-            ch: None
+            pending: None,
+            positional: false,
         };
-        lexer.read_char();
 
-        while let Some(token) = lexer.next_token() {
+        while let Some(token) = lexer.next_token()? {
             tokens.push(token);
         }
 
-        tokens
+        Ok(tokens)
     }
 
-    fn read_char(&mut self) {
-        self.ch = self.input.chars().nth(self.read_position);
-        self.position = self.read_position;
-        self.read_position += 1;
+    fn ch(&self) -> Option<char> {
+        self.input.get(self.position).copied()
     }
 
     fn peak_char(&self) -> Option<char> {
-        self.input.chars().nth(self.read_position)
+        self.input.get(self.position + 1).copied()
     }
 
-    fn read_str(&mut self) -> String {
-        let pos = self.position;
-        while self.peak_char().is_some_and(|x| x != ' ')  {
-            self.read_char();
+    fn error(&self, message: &str, position: usize) -> LexError {
+        LexError {
+            message: message.to_string(),
+            position,
         }
-        return self.input[pos..=self.position].to_string();
     }
 
-    fn read_input(&mut self, quote: char) -> String {
-        let pos = self.position;
-        while self.peak_char().is_some_and(|x| x != quote) {
-            self.read_char(); 
+    fn skip_whitespace(&mut self) {
+        while self.ch().is_some_and(is_whitespace) {
+            self.position += 1;
         }
-        return self.input[pos..=self.position].to_string();
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.ch == Some(' ')  {
-            self.read_char();
+    fn read_single_quoted(&mut self, value: &mut String) -> Result<(), LexError> {
+        let start = self.position;
+        self.position += 1;
+        loop {
+            match self.ch() {
+                Some('\'') => break,
+                Some(ch) => value.push(ch),
+                None => return Err(self.error("Unterminated single quote", start)),
+            }
+            self.position += 1;
         }
+        self.position += 1;
+        Ok(())
     }
-    // fn has_next_token(&self) -> bool {
-    //     self.ch.is_some() 
-    // }
 
-    fn next_token(&mut self) -> Option<Token> {
-        self.skip_whitespace();        
+    fn read_double_quoted(&mut self, value: &mut String) -> Result<(), LexError> {
+        let start = self.position;
+        self.position += 1;
+        loop {
+            match self.ch() {
+                Some('"') => break,
+                Some('\\') => match self.peak_char() {
+                    Some('\n') => {
+                        self.position += 1;
+                    },
+                    Some(ch @ ('$' | '`' | '"' | '\\')) => {
+                        value.push(ch);
+                        self.position += 1;
+                    },
+                    _ => value.push('\\'),
+                },
+                Some(ch) => value.push(ch),
+                None => return Err(self.error("Unterminated double quote", start)),
+            }
+            self.position += 1;
+        }
+        self.position += 1;
+        Ok(())
+    }
 
-        let res = if let Some(token) = self.ch {
-            match token {
-                '"' => {
-                    self.read_char();
-                    let value = self.read_input('"');
-                    self.read_char();
-                    Some(Token::Str(value))
+    fn read_ansi_c_quoted(&mut self, value: &mut String) -> Result<(), LexError> {
+        let start = self.position;
+        self.position += 2;
+        loop {
+            match self.ch() {
+                Some('\'') => break,
+                Some('\\') => {
+                    self.position += 1;
+                    let escaped = match self.ch() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('a') => '\x07',
+                        Some('b') => '\x08',
+                        Some('e') | Some('E') => '\x1b',
+                        Some('f') => '\x0c',
+                        Some('v') => '\x0b',
+                        Some('0') => '\0',
+                        Some(ch @ ('\\' | '\'' | '"' | '?')) => ch,
+                        Some(ch) => {
+                            value.push('\\');
+                            ch
+                        },
+                        None => return Err(self.error("Unterminated ANSI-C quote", start)),
+                    };
+                    value.push(escaped);
                 },
-                '\'' => {
-                    self.read_char();
-                    let value = self.read_input('\'');
-                    self.read_char();
-                    Some(Token::Str(value))
-                }
-                '-' => {
-                    if self.peak_char() == Some('-') {
-                        self.read_char();
-                        self.read_char();
-                        let value = self.read_str(); 
-                        Some(Token::FlagLong(value))
-                    // In case the dash is its own, we consider this to simply be a string
-                    // This is the case for something like "cd -".
-                    // TODO: check if this comment is correct and the abastraction is correct.
-                    } else if self.peak_char() == Some(' ') || self.peak_char() == None {
-                        Some(Token::Str("-".to_string()))
-                    } else {
-                        self.read_char();
-                        let value = self.read_str(); 
-                        if value.len() < 2 {
-                            let value = value.chars().next().expect("Short flag value has a character");
-                            Some(Token::FlagShort(value))
-                        } else {
-                            let value = value.chars().collect();
-                            Some(Token::FlagCombo(value))
-                        }
+                Some(ch) => value.push(ch),
+                None => return Err(self.error("Unterminated ANSI-C quote", start)),
+            }
+            self.position += 1;
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn starts_with(&self, value: &str) -> bool {
+        value.chars().enumerate().all(|(i, ch)| self.input.get(self.position + i) == Some(&ch))
+    }
+
+    fn read_operator(&mut self) -> Option<Token> {
+        let operators = [
+            ("2>&1", Token::Redirect(Redirect::ErrToOut)),
+            ("2>", Token::Redirect(Redirect::Err)),
+            (">>", Token::Redirect(Redirect::Append)),
+            (">", Token::Redirect(Redirect::Out)),
+            ("<", Token::Redirect(Redirect::In)),
+            ("&&", Token::Connector(Connector::And)),
+            ("||", Token::Connector(Connector::Or)),
+            ("|", Token::Connector(Connector::Pipe)),
+            (";", Token::Connector(Connector::Then)),
+        ];
+
+        for (value, token) in operators {
+            if self.starts_with(value) {
+                self.position += value.chars().count();
+                return Some(token);
+            }
+        }
+
+        None
+    }
+
+    // The current char is the `$` of `$(`. Reads until the matching `)`
+    // and returns what is in between.
+    fn read_substitution(&mut self) -> Result<String, LexError> {
+        let start = self.position;
+        let mut depth = 0;
+        let mut quote = None;
+        self.position += 2;
+
+        let content_start = self.position;
+        loop {
+            match (self.ch(), quote) {
+                (None, _) => return Err(self.error("Unterminated command substitution", start)),
+                (Some('\\'), _) => self.position += 1,
+                (Some(ch @ ('\'' | '"')), None) => quote = Some(ch),
+                (Some(ch), Some(q)) if ch == q => quote = None,
+                (Some('('), None) => depth += 1,
+                (Some(')'), None) if depth == 0 => break,
+                (Some(')'), None) => depth -= 1,
+                _ => {}
+            }
+            self.position += 1;
+        }
+
+        let content = self.input[content_start..self.position].iter().collect();
+        self.position += 1;
+        Ok(content)
+    }
+
+    fn read_word(&mut self) -> Result<Word, LexError> {
+        let mut value = String::new();
+        let quoted = matches!(self.ch(), Some('\'' | '"' | '\\'))
+            || (self.ch() == Some('$') && self.peak_char() == Some('\''));
+
+        while let Some(ch) = self.ch() {
+            match ch {
+                ch if is_whitespace(ch) || is_operator(ch) => break,
+                '$' if self.peak_char() == Some('(') => {
+                    let content = self.read_substitution()?;
+                    value.push_str(&format!("$({content})"));
+                },
+                '\'' => self.read_single_quoted(&mut value)?,
+                '"' => self.read_double_quoted(&mut value)?,
+                '$' if self.peak_char() == Some('\'') => self.read_ansi_c_quoted(&mut value)?,
+                '\\' => {
+                    match self.peak_char() {
+                        // line continuation
+                        Some('\n') => {},
+                        Some(ch) => value.push(ch),
+                        None => return Err(self.error("Nothing to escape after '\\'", self.position)),
                     }
+                    self.position += 2;
                 },
-                c => {
-                    let value = self.read_str(); 
-                    Some(Token::Str(value))
+                ch => {
+                    value.push(ch);
+                    self.position += 1;
+                }
+            }
+        }
+
+        Ok(Word { value, quoted })
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        if let Some(token) = self.pending.take() {
+            return Ok(Some(token));
+        }
+
+        self.skip_whitespace();        
+
+        if self.ch().is_none() {
+            return Ok(None);
+        }
+
+        if let Some(token) = self.read_operator() {
+            if let Token::Connector(_) = token {
+                self.positional = false;
+            }
+            return Ok(Some(token));
+        }
+
+        if matches!(self.ch(), Some('(' | ')' | '&')) {
+            return Err(self.error("Subshells and background jobs are not supported", self.position));
+        }
+
+        if self.starts_with("$(") {
+            let start = self.position;
+            let content = self.read_substitution()?;
+            if self.ch().is_none_or(|ch| is_whitespace(ch) || is_operator(ch)) {
+                let tokens = InputCmdLexer::compile(&content).map_err(|err| {
+                    self.error(&err.message, start + 2 + err.position)
+                })?;
+                return Ok(Some(Token::Substitution(tokens)));
+            }
+            self.position = start;
+        }
+
+        let word = self.read_word()?;
+        if word.quoted || self.positional {
+            return Ok(Some(Token::Str(word.value)));
+        }
+
+        let token = if word.value == "--" {
+            self.positional = true;
+            Token::Delimiter
+        } else if let Some(value) = word.value.strip_prefix("--") {
+            if let Some((flag, input)) = value.split_once('=') {
+                // `--flag=` with nothing after it, but `--flag=""` is an empty value
+                if input.is_empty() && self.input.get(self.position - 1) == Some(&'=') {
+                    return Err(self.error(&format!("Missing the value of --{flag} after '='"), self.position - 1));
                 }
+                self.pending = Some(Token::Str(input.to_string()));
+                Token::FlagLong(flag.to_string())
+            } else {
+                Token::FlagLong(value.to_string())
+            }
+        // In case the dash is its own, we consider this to simply be a string
+        // This is the case for something like "cd -".
+        } else if word.value == "-" {
+            Token::Str(word.value)
+        } else if let Some(value) = word.value.strip_prefix('-') {
+            let mut chars: Vec<char> = value.chars().collect();
+            if chars.len() < 2 {
+                Token::FlagShort(chars.remove(0))
+            } else {
+                Token::FlagCombo(chars)
             }
         } else {
-            None
+            Token::Str(word.value)
         };
-        self.read_char();
-        res
+
+        Ok(Some(token))
     }
 }
 
-// TODO: implement the possibility to have numbers inside a string 
-// that does not start with a number.
-// Currently, it breaks, because we use '0' as an EOF enum
-fn is_str_letter(input: Option<char>) -> bool {
-    if let Some(input) = input {
-        match input {
-            'a'..='z' | 'A'..='Z' | '-' | '_' | '0'..='9'  => true,
-            _ => false
-        }
-    } else {
-        false
-    }
+fn is_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\n')
+}
+
+fn is_operator(ch: char) -> bool {
+    matches!(ch, '|' | '&' | ';' | '<' | '>' | '(' | ')')
 }
 
 
@@ -175,7 +387,7 @@ mod tests {
             Token::Str(String::from("client-0")),
             Token::Str(String::from("Hello, World!")),
         ];
-        let result = InputCmdLexer::compile(&input);
+        let result = InputCmdLexer::compile(input).unwrap();
         assert_eq!(result, exp);
     }
      
@@ -187,7 +399,7 @@ mod tests {
             Token::FlagShort('t'),
             Token::Str(String::from("pane-1")),
         ];
-        let result = InputCmdLexer::compile(&input);
+        let result = InputCmdLexer::compile(input).unwrap();
         assert_eq!(result, exp);
     }
      
@@ -200,7 +412,7 @@ mod tests {
             Token::Str(String::from("1")),
             Token::Str(String::from("/path/to/file.txt")),
         ];
-        let result = InputCmdLexer::compile(&input);
+        let result = InputCmdLexer::compile(input).unwrap();
         assert_eq!(result, exp);
     }
 
@@ -214,7 +426,7 @@ mod tests {
             Token::FlagShort('b'),
             Token::Str(String::from("5")),
         ];
-        let result = InputCmdLexer::compile(&input);
+        let result = InputCmdLexer::compile(input).unwrap();
         assert_eq!(result, exp);
     }
 
@@ -230,7 +442,7 @@ mod tests {
             Token::Str(String::from("echo File exists")),
             Token::Str(String::from("echo File does not exist")),
         ];
-        let result = InputCmdLexer::compile(&input);
+        let result = InputCmdLexer::compile(input).unwrap();
         assert_eq!(result, exp);
     }
 
@@ -240,7 +452,7 @@ mod tests {
         let exp = vec![
             Token::FlagCombo(vec!['l', 'a']),
         ];
-        let result = InputCmdLexer::compile(&input);
+        let result = InputCmdLexer::compile(input).unwrap();
         assert_eq!(result, exp);
     }
 
@@ -250,7 +462,213 @@ mod tests {
         let exp = vec![
             Token::FlagLong("long".to_string()),
         ];
-        let result = InputCmdLexer::compile(&input);
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn long_flag_with_explicit_input() {
+        let input = "git clone --depth=1 repo";
+        let exp = vec![
+            Token::Str(String::from("git")),
+            Token::Str(String::from("clone")),
+            Token::FlagLong("depth".to_string()),
+            Token::Str(String::from("1")),
+            Token::Str(String::from("repo")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn long_flag_with_negative_input() {
+        let input = "command --option=-2";
+        let exp = vec![
+            Token::Str(String::from("command")),
+            Token::FlagLong("option".to_string()),
+            Token::Str(String::from("-2")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
         assert_eq!(result, exp);
     }
+
+    #[test]
+    fn long_flag_with_empty_input() {
+        let result = InputCmdLexer::compile("git log --author= main");
+        assert_eq!(result, Err(LexError {
+            message: "Missing the value of --author after '='".to_string(),
+            position: 16,
+        }));
+
+        let result = InputCmdLexer::compile("git log --author=\"\"").unwrap();
+        assert_eq!(result[3], Token::Str(String::new()));
+    }
+
+    #[test]
+    fn delimiter() {
+        let input = "cargo run -- -v --release";
+        let exp = vec![
+            Token::Str(String::from("cargo")),
+            Token::Str(String::from("run")),
+            Token::Delimiter,
+            Token::Str(String::from("-v")),
+            Token::Str(String::from("--release")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn escapes_and_nested_quotes() {
+        let input = r#"git commit -m "fix \"quote\" bug""#;
+        let exp = vec![
+            Token::Str(String::from("git")),
+            Token::Str(String::from("commit")),
+            Token::FlagShort('m'),
+            Token::Str(String::from("fix \"quote\" bug")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn escaped_space_outside_quotes() {
+        let input = r"cat my\ file.txt 'it''s'";
+        let exp = vec![
+            Token::Str(String::from("cat")),
+            Token::Str(String::from("my file.txt")),
+            Token::Str(String::from("its")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn adjacent_fragments_are_one_word() {
+        let input = r#"echo "a"'b'c"#;
+        let exp = vec![
+            Token::Str(String::from("echo")),
+            Token::Str(String::from("abc")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn ansi_c_quotes() {
+        let input = r"printf $'a\tb\n'";
+        let exp = vec![
+            Token::Str(String::from("printf")),
+            Token::Str(String::from("a\tb\n")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn variables_are_kept() {
+        let input = "echo $HOME \"${USER}s\"";
+        let exp = vec![
+            Token::Str(String::from("echo")),
+            Token::Str(String::from("$HOME")),
+            Token::Str(String::from("${USER}s")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn tabs_are_whitespace() {
+        let input = "ls\t-la";
+        let exp = vec![
+            Token::Str(String::from("ls")),
+            Token::FlagCombo(vec!['l', 'a']),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn quoted_dash_is_a_string() {
+        let input = "grep \"-v\"";
+        let exp = vec![
+            Token::Str(String::from("grep")),
+            Token::Str(String::from("-v")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let result = InputCmdLexer::compile("echo \"hello");
+        assert_eq!(result, Err(LexError {
+            message: "Unterminated double quote".to_string(),
+            position: 5,
+        }));
+
+        let result = InputCmdLexer::compile("echo 'hello");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn pipeline_and_redirect() {
+        let input = "ps aux|grep 'a|b' > out.txt 2>&1";
+        let exp = vec![
+            Token::Str(String::from("ps")),
+            Token::Str(String::from("aux")),
+            Token::Connector(Connector::Pipe),
+            Token::Str(String::from("grep")),
+            Token::Str(String::from("a|b")),
+            Token::Redirect(Redirect::Out),
+            Token::Str(String::from("out.txt")),
+            Token::Redirect(Redirect::ErrToOut),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn chains() {
+        let input = "make && make install || echo fail; ls -- -x | wc -l";
+        let exp = vec![
+            Token::Str(String::from("make")),
+            Token::Connector(Connector::And),
+            Token::Str(String::from("make")),
+            Token::Str(String::from("install")),
+            Token::Connector(Connector::Or),
+            Token::Str(String::from("echo")),
+            Token::Str(String::from("fail")),
+            Token::Connector(Connector::Then),
+            Token::Str(String::from("ls")),
+            Token::Delimiter,
+            Token::Str(String::from("-x")),
+            Token::Connector(Connector::Pipe),
+            Token::Str(String::from("wc")),
+            Token::FlagShort('l'),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn command_substitution() {
+        let input = "kill $(pgrep -f \"my app\") v$(date)";
+        let exp = vec![
+            Token::Str(String::from("kill")),
+            Token::Substitution(vec![
+                Token::Str(String::from("pgrep")),
+                Token::FlagShort('f'),
+                Token::Str(String::from("my app")),
+            ]),
+            Token::Str(String::from("v$(date)")),
+        ];
+        let result = InputCmdLexer::compile(input).unwrap();
+        assert_eq!(result, exp);
+    }
+
+    #[test]
+    fn unterminated_substitution() {
+        assert!(InputCmdLexer::compile("kill $(pgrep app").is_err());
+    }
 }
@@ -1,4 +1,5 @@
 use std::fmt;
+use super::parser::{Connector, Redirect};
 
 /// List of tood to implement for the lexer
 /// -----
@@ -7,13 +8,13 @@ use std::fmt;
 /// ---- 
 /// It starts with number, it is an intiger. Otherwise it is a string
 ///
-/// TODO: add a delimiter "--" token
+/// A delimiter "--" token
 /// ---
 /// example: cargo run -- arg
 ///
-/// TODO: add an explicit input for a flag with equal
+/// An explicit input for a flag with equal
 /// ---
-/// example: command --option=23
+/// example: command --option=<value>
 ///
 /// TODO: add support for negative numbers
 /// ---
@@ -30,7 +31,15 @@ pub enum Token {
     FlagLong(String),
     Multiple,
     Or,
+    Alias,
+    Eq,
+    Delimiter,
+    Connector(Connector),
+    Redirect(Redirect),
+    SubOpen,
+    SubClose,
     Str(String),
+    Quoted(String),
 }
 
 impl fmt::Display for Token {
@@ -45,60 +54,152 @@ impl fmt::Display for Token {
             Token::FlagLong(val) => write!(f, "--{val}"),
             Token::Multiple => write!(f, "..."),
             Token::Or => write!(f, "|"),
+            Token::Alias => write!(f, "/"),
+            Token::Eq => write!(f, "="),
+            Token::Delimiter => write!(f, "--"),
+            Token::Connector(val) => write!(f, "{val}"),
+            Token::Redirect(val) => write!(f, "{val}"),
+            Token::SubOpen => write!(f, "$("),
+            Token::SubClose => write!(f, ")"),
             Token::Str(val) => write!(f, "{val}"),
+            Token::Quoted(val) => write!(f, "\"{val}\""),
         }
     }
 }
 
+/// Where a token is in the pattern as char positions `start..end`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A pattern that can not be lexed or parsed and where the problem is.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self { message: message.into(), span }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.span.start)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 pub struct CmdLexer<'a > {
     input: &'a str, 
+    // The byte offset of each char, the positions count chars
+    chars: Vec<(usize, char)>,
     position: usize,
     read_position: usize,
-    ch: Option<char>
+    ch: Option<char>,
+    // `|` is an alternative inside of `[...]` and a pipe outside of it
+    sq_depth: usize,
+    // `>` closes a variable or it is a redirect
+    in_variable: bool,
 }
 
 impl<'a> CmdLexer<'a> {
     pub fn compile(input: &'a str) -> Vec<Token> {
+        Self::compile_with_spans(input)
+            .unwrap_or_else(|err| panic!("{err}"))
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    pub fn compile_with_spans(input: &'a str) -> Result<Vec<(Token, Span)>, ParseError> {
         let mut tokens = Vec::new();
         let mut lexer = Self {
             input,
+            chars: input.char_indices().collect(),
             position: 0,
             read_position: 0,
-            ch: None
+            ch: None,
+            sq_depth: 0,
+            in_variable: false,
         };
         lexer.read_char();
 
-        while let Some(token) = lexer.next_token() {
+        while let Some(token) = lexer.next_token()? {
             tokens.push(token);
         }
 
-        tokens
+        Ok(tokens)
+    }
+
+    fn char_at(&self, position: usize) -> Option<char> {
+        self.chars.get(position).map(|(_, ch)| *ch)
+    }
+
+    // The input from the char at `position` on.
+    fn rest(&self, position: usize) -> &'a str {
+        let offset = self.chars.get(position).map_or(self.input.len(), |(offset, _)| *offset);
+        &self.input[offset..]
+    }
+
+    // The input from the char at `start` up to and with the current char.
+    fn read_since(&self, start: usize) -> String {
+        let end = self.rest(self.position + 1).len();
+        let rest = self.rest(start);
+        rest[..rest.len() - end].to_string()
     }
 
     fn read_char(&mut self) {
-        self.ch = self.input.chars().nth(self.read_position);
+        self.ch = self.char_at(self.read_position);
         self.position = self.read_position;
         self.read_position += 1;
     }
 
     fn peak_char(&self) -> Option<char> {
-        self.input.chars().nth(self.read_position)
+        self.char_at(self.read_position)
     }
 
     fn read_str(&mut self) -> String {
         let pos = self.position;
-        while is_str_letter(self.peak_char()) {
+        // `file...` is the word and a repeat
+        while is_str_letter(self.peak_char()) && !self.is_repeat_next() {
             self.read_char();
         }
-        return self.input[pos..=self.position].to_string();
+        self.read_since(pos)
     }
 
-    fn read_literal(&mut self) -> String {
-        let pos = self.position;
-        while self.peak_char() != Some('"') {
-            self.read_char();
+    // The current char is the opening quote. Reads until the closing
+    // quote, where `\"` and `\\` are the only escapes.
+    fn read_literal(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        let mut value = String::new();
+        loop {
+            match self.peak_char() {
+                Some('"') => {
+                    self.read_char();
+                    break;
+                },
+                Some('\\') if matches!(self.char_at(self.read_position + 1), Some('"' | '\\')) => {
+                    self.read_char();
+                    self.read_char();
+                    value.push(self.ch.expect("Escaped char"));
+                },
+                Some(ch) => {
+                    self.read_char();
+                    value.push(ch);
+                },
+                None => {
+                    let span = Span { start, end: self.read_position };
+                    return Err(ParseError::new("Could not enclose explicit string with '\"'", span));
+                }
+            }
         }
-        return self.input[pos..=self.position].to_string();
+        Ok(value)
     }
 
     fn read_flag(&mut self) -> String {
@@ -106,7 +207,16 @@ impl<'a> CmdLexer<'a> {
         while is_flag_letter(self.peak_char()) {
             self.read_char();
         }
-        return self.input[pos..=self.position].to_string();
+        self.read_since(pos)
+    }
+
+    // The last three dots of a word are a repeat, `v1....` is `v1.` and `...`.
+    fn is_repeat_next(&self) -> bool {
+        is_repeat(self.rest(self.read_position))
+    }
+
+    fn is_repeat_here(&self) -> bool {
+        is_repeat(self.rest(self.position))
     }
 
     fn skip_whitespace(&mut self) {
@@ -115,26 +225,65 @@ impl<'a> CmdLexer<'a> {
         }
     }
 
-    fn consume_dots(&mut self) {
-        while self.peak_char() == Some('.') {
-            self.read_char();
-        }
+    fn follows_word(&self) -> bool {
+        self.position > 0 && self.char_at(self.position - 1).is_some_and(|x| !matches!(x, ' ' | '(' | '[' | '|'))
     }
 
-    fn next_token(&mut self) -> Option<Token> {
+    fn next_token(&mut self) -> Result<Option<(Token, Span)>, ParseError> {
         self.skip_whitespace();        
 
         if let Some(token) = self.ch {
+            let start = self.position;
             let token = match token {
-                '[' => Some(Token::LSq),
-                ']' => Some(Token::RSq),
-                '<' => Some(Token::LAr),
-                '>' => Some(Token::RAr),
+                '[' => {
+                    self.sq_depth += 1;
+                    Some(Token::LSq)
+                },
+                ']' => {
+                    self.sq_depth = self.sq_depth.saturating_sub(1);
+                    Some(Token::RSq)
+                },
+                '<' if self.peak_char().is_none() || self.peak_char() == Some(' ') => {
+                    Some(Token::Redirect(Redirect::In))
+                },
+                '<' => {
+                    self.in_variable = true;
+                    Some(Token::LAr)
+                },
+                '>' if self.in_variable => {
+                    self.in_variable = false;
+                    Some(Token::RAr)
+                },
+                '>' => {
+                    if self.peak_char() == Some('>') {
+                        self.read_char();
+                        Some(Token::Redirect(Redirect::Append))
+                    } else {
+                        Some(Token::Redirect(Redirect::Out))
+                    }
+                },
+                '2' if self.peak_char() == Some('>') => {
+                    self.read_char();
+                    if self.rest(self.read_position).starts_with("&1") {
+                        self.read_char();
+                        self.read_char();
+                        Some(Token::Redirect(Redirect::ErrToOut))
+                    } else {
+                        Some(Token::Redirect(Redirect::Err))
+                    }
+                },
+                '-' if is_word_end(self.peak_char()) || self.is_repeat_next() => {
+                    Some(Token::Str("-".to_string()))
+                },
                 '-' => {
                     if self.peak_char() == Some('-') {
                         self.read_char();
-                        self.read_char();
-                        Some(Token::FlagLong(self.read_flag()))
+                        if is_word_end(self.peak_char()) {
+                            Some(Token::Delimiter)
+                        } else {
+                            self.read_char();
+                            Some(Token::FlagLong(self.read_flag()))
+                        }
                     } else {
                         self.read_char();
                         let value = self.read_flag();
@@ -146,34 +295,61 @@ impl<'a> CmdLexer<'a> {
                         }
                     }
                 },
-                '"' => {
+                '"' => Some(Token::Quoted(self.read_literal()?)),
+                // `<file>...` repeats the word before, `..` on its own is a word
+                '.' if self.follows_word() && self.is_repeat_here() => {
                     self.read_char();
-                    let value = self.read_literal();
                     self.read_char();
+                    Some(Token::Multiple)
+                },
 
-                    if self.ch != Some('"') {
-                        panic!("Could not enclose explicit string with '{}'", self.ch.unwrap());
+                // `[-a | -b]` alternatives and `<mode:soft|hard>` choices
+                '|' if self.sq_depth > 0 || self.in_variable => Some(Token::Or),
+                '|' => {
+                    if self.peak_char() == Some('|') {
+                        self.read_char();
+                        Some(Token::Connector(Connector::Or))
+                    } else {
+                        Some(Token::Connector(Connector::Pipe))
                     }
-                    Some(Token::Str(value)) 
                 },
-                '.' => {
-                    self.consume_dots();
-                    Some(Token::Multiple)
+                '&' if self.peak_char() == Some('&') => {
+                    self.read_char();
+                    Some(Token::Connector(Connector::And))
+                },
+                ';' => Some(Token::Connector(Connector::Then)),
+                '$' if self.peak_char() == Some('(') => {
+                    self.read_char();
+                    Some(Token::SubOpen)
                 },
-                '|' => Some(Token::Or),
-                'a'..='z' | 'A'..='Z' => Some(Token::Str(self.read_str())),
+                ')' => Some(Token::SubClose),
+                '=' => Some(Token::Eq),
+                '/' if self.peak_char() == Some('-') => Some(Token::Alias),
+                c if is_str_letter(Some(c)) => Some(Token::Str(self.read_str())),
                 c => {
-                    panic!("Could not parser {c} at position {p}", c = c, p = self.position);
+                    let span = Span { start, end: start + 1 };
+                    return Err(ParseError::new(format!("Unexpected '{c}'"), span));
                 }
             };
+            let span = Span { start, end: self.position + 1 };
             self.read_char();
-            token
+            Ok(token.map(|token| (token, span)))
         } else {
-            None
+            Ok(None)
         }
     }
 }
 
+fn is_repeat(rest: &str) -> bool {
+    rest.starts_with("...") && !rest[3..].starts_with('.')
+}
+
+// A lone `-` or `--` is a word when nothing else follows it, like
+// `cat -`, `[-]` or `$(cat -)`.
+fn is_word_end(input: Option<char>) -> bool {
+    matches!(input, None | Some(' ' | ']' | ')'))
+}
+
 fn is_flag_letter( input: Option<char>) -> bool {
     if let Some(input) = input {
         match input {
@@ -185,13 +361,13 @@ fn is_flag_letter( input: Option<char>) -> bool {
     }
 }
 
-// TODO: implement the possibility to have numbers inside a string 
-// that does not start with a number.
-// Currently, it breaks, because we use '0' as an EOF enum
-fn is_str_letter(input: Option<char>) -> bool {
+// Bare words can have paths, versions, globs and refs in them, e.g.
+// `../src`, `v1.2`, `*.txt` or `HEAD~`. Anything else has to be quoted.
+pub fn is_str_letter(input: Option<char>) -> bool {
     if let Some(input) = input {
         match input {
-            'a'..='z' | 'A'..='Z' | '-' | '_'  => true,
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '~' | '/' | '.' 
+                | '*' | ':' | '@' | '+' | '%' | ',' | '^' | '='  => true,
             _ => false
         }
     } else {
@@ -487,4 +663,272 @@ mod tests {
        let result = super::CmdLexer::compile(&input);
        assert_eq!(result, exp);
    }
+
+   #[test]
+   fn flag_alias() {
+       let input = "git add -A/--all";
+       let exp = vec![
+           super::Token::Str(String::from("git")),
+           super::Token::Str(String::from("add")),
+           super::Token::FlagShort(String::from("A")),
+           super::Token::Alias,
+           super::Token::FlagLong(String::from("all")),
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn flag_with_equal_input() {
+       let input = "--depth=<depth>";
+       let exp = vec![
+           super::Token::FlagLong(String::from("depth")),
+           super::Token::Eq,
+           super::Token::LAr,
+           super::Token::Str(String::from("depth")),
+           super::Token::RAr,
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn delimiter() {
+       let input = "cargo run -- <arg>";
+       let exp = vec![
+           super::Token::Str(String::from("cargo")),
+           super::Token::Str(String::from("run")),
+           super::Token::Delimiter,
+           super::Token::LAr,
+           super::Token::Str(String::from("arg")),
+           super::Token::RAr,
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn pipe_and_alternative() {
+       let input = "ps aux | grep [-i | -v] <name>";
+       let exp = vec![
+           super::Token::Str(String::from("ps")),
+           super::Token::Str(String::from("aux")),
+           super::Token::Connector(super::Connector::Pipe),
+           super::Token::Str(String::from("grep")),
+           super::Token::LSq,
+           super::Token::FlagShort(String::from("i")),
+           super::Token::Or,
+           super::Token::FlagShort(String::from("v")),
+           super::Token::RSq,
+           super::Token::LAr,
+           super::Token::Str(String::from("name")),
+           super::Token::RAr,
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn chains_and_redirects() {
+       let input = "make && cat < <in> >> <out> 2>&1 || ls 2> <err>; ls";
+       let exp = vec![
+           super::Token::Str(String::from("make")),
+           super::Token::Connector(super::Connector::And),
+           super::Token::Str(String::from("cat")),
+           super::Token::Redirect(super::Redirect::In),
+           super::Token::LAr,
+           super::Token::Str(String::from("in")),
+           super::Token::RAr,
+           super::Token::Redirect(super::Redirect::Append),
+           super::Token::LAr,
+           super::Token::Str(String::from("out")),
+           super::Token::RAr,
+           super::Token::Redirect(super::Redirect::ErrToOut),
+           super::Token::Connector(super::Connector::Or),
+           super::Token::Str(String::from("ls")),
+           super::Token::Redirect(super::Redirect::Err),
+           super::Token::LAr,
+           super::Token::Str(String::from("err")),
+           super::Token::RAr,
+           super::Token::Connector(super::Connector::Then),
+           super::Token::Str(String::from("ls")),
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn substitution() {
+       let input = "kill $(pgrep <name>)";
+       let exp = vec![
+           super::Token::Str(String::from("kill")),
+           super::Token::SubOpen,
+           super::Token::Str(String::from("pgrep")),
+           super::Token::LAr,
+           super::Token::Str(String::from("name")),
+           super::Token::RAr,
+           super::Token::SubClose,
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn spans() {
+       let input = "git \"~\"<user> HEAD~<n>";
+       let exp = vec![
+           (super::Token::Str(String::from("git")), super::Span { start: 0, end: 3 }),
+           (super::Token::Quoted(String::from("~")), super::Span { start: 4, end: 7 }),
+           (super::Token::LAr, super::Span { start: 7, end: 8 }),
+           (super::Token::Str(String::from("user")), super::Span { start: 8, end: 12 }),
+           (super::Token::RAr, super::Span { start: 12, end: 13 }),
+           (super::Token::Str(String::from("HEAD~")), super::Span { start: 14, end: 19 }),
+           (super::Token::LAr, super::Span { start: 19, end: 20 }),
+           (super::Token::Str(String::from("n")), super::Span { start: 20, end: 21 }),
+           (super::Token::RAr, super::Span { start: 21, end: 22 }),
+       ];
+       let result = super::CmdLexer::compile_with_spans(input).unwrap();
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn non_ascii_spans() {
+       let exp = vec![
+           (super::Token::Str(String::from("echo")), super::Span { start: 0, end: 4 }),
+           (super::Token::Quoted(String::from("日本")), super::Span { start: 5, end: 9 }),
+           (super::Token::Str(String::from("abcdef")), super::Span { start: 10, end: 16 }),
+       ];
+       let result = super::CmdLexer::compile_with_spans("echo \"日本\" abcdef").unwrap();
+       assert_eq!(result, exp);
+
+       let result = super::CmdLexer::compile("echo \"éé\" ab...");
+       assert_eq!(result[1..], [
+           super::Token::Quoted(String::from("éé")),
+           super::Token::Str(String::from("ab")),
+           super::Token::Multiple,
+       ]);
+
+       let result = super::CmdLexer::compile_with_spans("echo é");
+       assert_eq!(result, Err(super::ParseError::new("Unexpected 'é'", super::Span { start: 5, end: 6 })));
+   }
+
+   #[test]
+   fn word_parts() {
+       let input = "origin/<branch> <file>.txt";
+       let exp = vec![
+           super::Token::Str(String::from("origin/")),
+           super::Token::LAr,
+           super::Token::Str(String::from("branch")),
+           super::Token::RAr,
+           super::Token::LAr,
+           super::Token::Str(String::from("file")),
+           super::Token::RAr,
+           super::Token::Str(String::from(".txt")),
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn bare_words() {
+       let input = "cd .. / - ../src v1.2 *.txt 2 <file>...";
+       let exp = vec![
+           super::Token::Str(String::from("cd")),
+           super::Token::Str(String::from("..")),
+           super::Token::Str(String::from("/")),
+           super::Token::Str(String::from("-")),
+           super::Token::Str(String::from("../src")),
+           super::Token::Str(String::from("v1.2")),
+           super::Token::Str(String::from("*.txt")),
+           super::Token::Str(String::from("2")),
+           super::Token::LAr,
+           super::Token::Str(String::from("file")),
+           super::Token::RAr,
+           super::Token::Multiple,
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn quoted_literals() {
+       let input = r#"cd ["~"] "a b|c" "say \"hi\"" """#;
+       let exp = vec![
+           super::Token::Str(String::from("cd")),
+           super::Token::LSq,
+           super::Token::Quoted(String::from("~")),
+           super::Token::RSq,
+           super::Token::Quoted(String::from("a b|c")),
+           super::Token::Quoted(String::from("say \"hi\"")),
+           super::Token::Quoted(String::from("")),
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn choice_variable() {
+       let input = "git reset <mode:soft|mixed|hard> | cat";
+       let exp = vec![
+           super::Token::Str(String::from("git")),
+           super::Token::Str(String::from("reset")),
+           super::Token::LAr,
+           super::Token::Str(String::from("mode:soft")),
+           super::Token::Or,
+           super::Token::Str(String::from("mixed")),
+           super::Token::Or,
+           super::Token::Str(String::from("hard")),
+           super::Token::RAr,
+           super::Token::Connector(super::Connector::Pipe),
+           super::Token::Str(String::from("cat")),
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn dash_before_closing() {
+       let input = "[a | -] $(cat -) [--]";
+       let exp = vec![
+           super::Token::LSq,
+           super::Token::Str(String::from("a")),
+           super::Token::Or,
+           super::Token::Str(String::from("-")),
+           super::Token::RSq,
+           super::Token::SubOpen,
+           super::Token::Str(String::from("cat")),
+           super::Token::Str(String::from("-")),
+           super::Token::SubClose,
+           super::Token::LSq,
+           super::Token::Delimiter,
+           super::Token::RSq,
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
+
+   #[test]
+   fn errors() {
+       let result = super::CmdLexer::compile_with_spans("echo \"hi");
+       assert_eq!(result, Err(super::ParseError::new(
+           "Could not enclose explicit string with '\"'",
+           super::Span { start: 5, end: 8 },
+       )));
+
+       let result = super::CmdLexer::compile_with_spans("git add (<path>)");
+       assert_eq!(result, Err(super::ParseError::new("Unexpected '('", super::Span { start: 8, end: 9 })));
+   }
+
+   #[test]
+   fn repeated_bare_word() {
+       let input = "cat file... ...";
+       let exp = vec![
+           super::Token::Str(String::from("cat")),
+           super::Token::Str(String::from("file")),
+           super::Token::Multiple,
+           super::Token::Str(String::from("...")),
+       ];
+       let result = super::CmdLexer::compile(input);
+       assert_eq!(result, exp);
+   }
 }
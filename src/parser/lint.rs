@@ -0,0 +1,278 @@
+use std::fmt;
+use super::lexer::Span;
+use super::parser::{CmdParser, CmdWord, Variable};
+
+/// How bad a problem in a pattern is. A pattern with an error can not
+/// be used at all, a warning is a pattern that works but is likely wrong.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// One problem of a pattern and the words of the pattern it is about.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Problem {
+    pub level: Level,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Problem {
+    fn warning(message: String, span: Span) -> Self {
+        Self { level: Level::Warning, message, span }
+    }
+
+    /// The `^^^` line to print under the pattern.
+    pub fn underline(&self) -> String {
+        let len = self.span.end.saturating_sub(self.span.start).max(1);
+        format!("{}{}", " ".repeat(self.span.start), "^".repeat(len))
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.level, self.message)
+    }
+}
+
+/// Checks a pattern for the mistakes that make a quest confusing or
+/// impossible to answer, ordered by where they are in the pattern.
+pub fn lint(pattern: &str) -> Vec<Problem> {
+    let ast = match CmdParser::compile_with_spans(pattern) {
+        Ok(ast) => ast,
+        Err(err) => {
+            return vec![Problem { level: Level::Error, message: err.message, span: err.span }];
+        }
+    };
+
+    let mut res = Vec::new();
+    lint_words(&ast, &mut res);
+    res.sort_by_key(|x| x.span.start);
+    res
+}
+
+fn lint_words(words: &[(CmdWord, Span)], res: &mut Vec<Problem>) {
+    for stage in words.split(|(word, _)| matches!(word, CmdWord::Connector(_))) {
+        first_word_literal(stage, res);
+        duplicate_flags(stage, res);
+        required_after_repeated(stage, res);
+        adjacent_optional_variables(stage, res);
+    }
+
+    for (word, span) in words {
+        unreachable_alternatives(word, *span, res);
+
+        if let CmdWord::Substitution { words } = word {
+            let words: Vec<(CmdWord, Span)> = words.iter().map(|x| (x.clone(), *span)).collect();
+            lint_words(&words, res);
+        }
+    }
+}
+
+// The first word is the command we are learning, like `git` or `tmux`.
+fn first_word_literal(stage: &[(CmdWord, Span)], res: &mut Vec<Problem>) {
+    match stage.first() {
+        Some((CmdWord::Literal { .. }, _)) | None => {},
+        Some((word, span)) => {
+            res.push(Problem::warning(format!("The command has to start with its name, not `{word}`"), *span));
+        }
+    }
+}
+
+// The flags of a word and if they come from a combo like `-la`.
+fn word_flags(word: &CmdWord) -> Vec<(String, bool)> {
+    match word {
+        CmdWord::FlagShort { value, .. } => vec![(format!("-{value}"), false)],
+        CmdWord::FlagLong { value, .. } => vec![(format!("--{value}"), false)],
+        CmdWord::FlagAlias { short, long, .. } => vec![(format!("-{short}"), false), (format!("--{long}"), false)],
+        CmdWord::FlagCombo { values } => values.iter().map(|x| (format!("-{x}"), true)).collect(),
+        CmdWord::Optional { word } | CmdWord::Repeated { word } => word_flags(word),
+        // Alternatives can share flags, as only one of them is used
+        CmdWord::BinaryOp { lhs, rhs, .. } => {
+            let mut flags = word_flags(lhs);
+            for flag in word_flags(rhs) {
+                if !flags.contains(&flag) {
+                    flags.push(flag);
+                }
+            }
+            flags
+        },
+        _ => vec![],
+    }
+}
+
+fn duplicate_flags(stage: &[(CmdWord, Span)], res: &mut Vec<Problem>) {
+    let mut seen: Vec<(String, bool, &CmdWord)> = Vec::new();
+
+    for (word, span) in stage {
+        for (flag, in_combo) in word_flags(word) {
+            if let Some((_, seen_in_combo, seen_word)) = seen.iter().find(|(x, _, _)| *x == flag) {
+                let message = if in_combo || *seen_in_combo {
+                    let (combo, other) = if in_combo { (word, *seen_word) } else { (*seen_word, word) };
+                    format!("Flag combo `{combo}` already has `{flag}` listed as `{other}`")
+                } else {
+                    format!("Flag `{flag}` is listed more than once")
+                };
+                res.push(Problem::warning(message, *span));
+            } else {
+                seen.push((flag, in_combo, word));
+            }
+        }
+    }
+}
+
+// A word that takes a positional value of the answer.
+fn is_positional(word: &CmdWord) -> bool {
+    match word {
+        CmdWord::Variable { required, .. } => *required,
+        CmdWord::Composite { .. } => true,
+        CmdWord::Repeated { word } => is_positional(word),
+        _ => false,
+    }
+}
+
+// `cp <src>... <dest>` reads all the values into `<src>` first.
+fn required_after_repeated(stage: &[(CmdWord, Span)], res: &mut Vec<Problem>) {
+    let mut repeated = None;
+
+    for (word, span) in stage {
+        if let Some(repeated) = repeated {
+            if is_positional(word) {
+                res.push(Problem::warning(format!("Required `{word}` comes after the repeated `{repeated}`"), *span));
+            }
+        }
+        if matches!(word, CmdWord::Repeated { .. }) && is_positional(word) {
+            repeated = Some(word);
+        }
+    }
+}
+
+fn is_optional_positional(word: &CmdWord) -> bool {
+    match word {
+        CmdWord::Variable { required, .. } => !*required,
+        CmdWord::Optional { word } => is_positional(word),
+        _ => false,
+    }
+}
+
+// With `[a] [b]` and one value in the answer we can't tell which one it is.
+fn adjacent_optional_variables(stage: &[(CmdWord, Span)], res: &mut Vec<Problem>) {
+    for pair in stage.windows(2) {
+        let ((first, first_span), (second, second_span)) = (&pair[0], &pair[1]);
+        if is_optional_positional(first) && is_optional_positional(second) {
+            let span = Span { start: first_span.start, end: second_span.end };
+            res.push(Problem::warning(format!("Optional `{first}` and `{second}` next to each other are ambiguous"), span));
+        }
+    }
+}
+
+fn alternatives(word: &CmdWord) -> Vec<&CmdWord> {
+    match word {
+        CmdWord::BinaryOp { lhs, rhs, .. } => {
+            let mut res = alternatives(lhs);
+            res.extend(alternatives(rhs));
+            res
+        },
+        word => vec![word],
+    }
+}
+
+// A string variable takes any value, so nothing after it is ever picked.
+fn is_catch_all(word: &CmdWord) -> bool {
+    match word {
+        CmdWord::Variable { kind: Variable::String, .. } => true,
+        CmdWord::Repeated { word } => is_catch_all(word),
+        _ => false,
+    }
+}
+
+fn unreachable_alternatives(word: &CmdWord, span: Span, res: &mut Vec<Problem>) {
+    match word {
+        CmdWord::BinaryOp { .. } => {
+            let words = alternatives(word);
+            for (idx, alternative) in words.iter().enumerate() {
+                let before = &words[..idx];
+                if before.iter().any(|x| x == alternative) {
+                    res.push(Problem::warning(format!("Alternative `{alternative}` is listed more than once"), span));
+                } else if let Some(catch_all) = before.iter().find(|x| is_catch_all(x)) {
+                    if is_positional(alternative) || matches!(alternative, CmdWord::Literal { .. }) {
+                        let message = format!("Alternative `{alternative}` is never used after `{catch_all}`");
+                        res.push(Problem::warning(message, span));
+                    }
+                }
+            }
+        },
+        CmdWord::Optional { word } | CmdWord::Repeated { word } => unreachable_alternatives(word, span, res),
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(pattern: &str) -> Vec<String> {
+        lint(pattern).into_iter().map(|x| x.message).collect()
+    }
+
+    #[test]
+    fn clean_patterns() {
+        assert!(lint("git commit -m <message>").is_empty());
+        assert!(lint("git reset [--hard | --soft] <commit>").is_empty());
+        assert!(lint("ls -la | grep <pattern>").is_empty());
+        assert!(lint("git add [<path>...]").is_empty());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let problems = lint("git add (<path>...)");
+        assert_eq!(problems[0].level, Level::Error);
+        assert_eq!(problems[0].message, "Unexpected '('");
+        assert_eq!(problems[0].underline(), "        ^");
+    }
+
+    #[test]
+    fn first_word() {
+        assert_eq!(messages("<cmd> --help"), vec!["The command has to start with its name, not `<cmd>`"]);
+        assert_eq!(messages("ls | -v"), vec!["The command has to start with its name, not `-v`"]);
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(messages("ls -a -l -a"), vec!["Flag `-a` is listed more than once"]);
+        assert_eq!(messages("ls -a/--all --all"), vec!["Flag `--all` is listed more than once"]);
+        assert_eq!(messages("ls -la -a"), vec!["Flag combo `-la` already has `-a` listed as `-a`"]);
+        assert_eq!(messages("ls [-a] -la"), vec!["Flag combo `-la` already has `-a` listed as `[-a]`"]);
+        assert!(lint("git reset [--hard | --soft] | grep --hard").is_empty());
+    }
+
+    #[test]
+    fn required_after_variadic() {
+        assert_eq!(messages("cp <src>... <dest>"), vec!["Required `<dest>` comes after the repeated `<src>...`"]);
+        assert!(lint("cp <src>... -t <dest>").is_empty());
+    }
+
+    #[test]
+    fn ambiguous_optionals() {
+        let problems = lint("git log [since] [until]");
+        assert_eq!(problems[0].message, "Optional `[since]` and `[until]` next to each other are ambiguous");
+        assert_eq!(problems[0].span, Span { start: 8, end: 23 });
+    }
+
+    #[test]
+    fn alternatives() {
+        assert_eq!(messages("git reset [--hard | --hard]"), vec!["Alternative `--hard` is listed more than once"]);
+        assert_eq!(messages("ls [<name> | a]"), vec!["Alternative `a` is never used after `<name>`"]);
+        assert!(lint("ls [<n:int> | a]").is_empty());
+    }
+}
@@ -2,4 +2,5 @@ pub mod lexer;
 pub mod parser;
 pub mod input_lexer;
 pub mod compare;
-
+pub mod lint;
+pub mod examples;
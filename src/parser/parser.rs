@@ -1,4 +1,4 @@
-use super::lexer::{Token, CmdLexer};
+use super::lexer::{is_str_letter, Token, CmdLexer, ParseError, Span};
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -6,10 +6,106 @@ pub enum BinaryOp {
     Or
 }
 
+/// Joins two commands of a pipeline or a chain.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Connector {
+    Pipe,
+    And,
+    Or,
+    Then,
+}
+
+impl fmt::Display for Connector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Connector::Pipe => write!(f, "|"),
+            Connector::And => write!(f, "&&"),
+            Connector::Or => write!(f, "||"),
+            Connector::Then => write!(f, ";"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Redirect {
+    Out,
+    Append,
+    In,
+    Err,
+    ErrToOut,
+}
+
+impl Redirect {
+    /// `2>&1` already says where it goes, the rest needs a target.
+    pub fn has_target(&self) -> bool {
+        *self != Redirect::ErrToOut
+    }
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Redirect::Out => write!(f, ">"),
+            Redirect::Append => write!(f, ">>"),
+            Redirect::In => write!(f, "<"),
+            Redirect::Err => write!(f, "2>"),
+            Redirect::ErrToOut => write!(f, "2>&1"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Variable {
     String,
-    Int
+    Int,
+    Path,
+    Url,
+    GitRef,
+    Duration,
+    Choice(Vec<String>),
+}
+
+impl Variable {
+    /// The type written after the name, like `<n:int>`, `<path:file>`
+    /// or the allowed values `<mode:soft|mixed|hard>`.
+    pub fn from_type(value: &str) -> Option<Self> {
+        match value {
+            value if value.contains('|') => {
+                Some(Variable::Choice(value.split('|').map(|x| x.to_string()).collect()))
+            },
+            "str" | "string" => Some(Variable::String),
+            "int" | "num" | "number" => Some(Variable::Int),
+            "path" | "file" | "dir" => Some(Variable::Path),
+            "url" => Some(Variable::Url),
+            "ref" | "branch" | "commit" | "tag" => Some(Variable::GitRef),
+            "duration" => Some(Variable::Duration),
+            _ => None,
+        }
+    }
+
+    /// The type a common name like `<url>` or `<branch>` hints at. It is
+    /// only a suggestion, a variable without a type takes any value.
+    pub fn suggest(name: &str) -> Option<Self> {
+        match name {
+            "n" | "count" | "depth" => Some(Variable::Int),
+            name => Variable::from_type(name).filter(|x| *x != Variable::String),
+        }
+    }
+
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Variable::String => write!(f, "str"),
+            Variable::Int => write!(f, "int"),
+            Variable::Path => write!(f, "path"),
+            Variable::Url => write!(f, "url"),
+            Variable::GitRef => write!(f, "ref"),
+            Variable::Duration => write!(f, "duration"),
+            Variable::Choice(values) => write!(f, "{}", values.join("|")),
+        }
+    }
 }
 
 /// This is the rough docs for the structure of the chunks
@@ -23,7 +119,7 @@ pub enum Variable {
 /// used as a variable value like strings, paths, ...
 /// == Variable
 /// - name : String
-/// - type: String | Int
+/// - type: String | Int | Path | Url | GitRef | Duration | Choice
 /// TODO: - multiple: Bool
 ///
 /// == FlagShort
@@ -37,6 +133,38 @@ pub enum Variable {
 /// == FlagCombo
 /// - values: Char[],
 ///
+/// == Delimiter
+/// the `--` that ends the options
+///
+/// == Connector
+/// joins two commands, the words before and after it are separate stages
+/// - `|`, `&&`, `||`, `;`
+///
+/// == Redirect
+/// - op: `>`, `>>`, `<`, `2>`, `2>&1`
+/// - target: Option<Input>
+///
+/// == Substitution
+/// - words: Inp[] of the command inside `$(...)`
+///
+/// == Composite
+/// one word made of literal and variable parts, e.g. `HEAD~<n>`
+/// - parts: (Literal | Variable)[]
+///
+/// == FlagAlias
+/// short and long spelling of the same flag, e.g. `-A/--all`
+/// - short: Char
+/// - long: String
+/// - input: Option<Input>
+///
+/// == Optional
+/// a word in `[...]` that can be left out
+/// - word: Inp
+///
+/// == Repeated
+/// a word followed by `...` that can be given one or more times
+/// - word: Inp
+///
 /// == Or
 /// - lhs: Inp,
 /// - rhs: Inp,
@@ -61,6 +189,29 @@ pub enum CmdWord {
     FlagCombo {
         values: Vec<char>,
     },
+    FlagAlias {
+        short: char,
+        long: String,
+        input: Box<Option<CmdWord>>,
+    },
+    Delimiter,
+    Connector(Connector),
+    Redirect {
+        op: Redirect,
+        target: Box<Option<CmdWord>>,
+    },
+    Substitution {
+        words: Vec<CmdWord>,
+    },
+    Composite {
+        parts: Vec<CmdWord>,
+    },
+    Optional {
+        word: Box<CmdWord>,
+    },
+    Repeated {
+        word: Box<CmdWord>,
+    },
     BinaryOp {
         op:  BinaryOp,
         lhs: Box<CmdWord>,
@@ -71,12 +222,18 @@ pub enum CmdWord {
 impl fmt::Display for CmdWord {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CmdWord::Literal{value} => write!(f, "{value}"),
-            CmdWord::Variable{ name, required, .. } => {
-                if *required {
-                    write!(f, "[{name}]")
+            CmdWord::Literal{value} => write!(f, "{}", fmt_literal(value, false)),
+            CmdWord::Variable{ name, kind, required } => {
+                let name = if *kind == Variable::String {
+                    name.to_string()
                 } else {
-                    write!(f, "<{name}>")
+                    format!("{name}:{kind}")
+                };
+                match (required, kind) {
+                    (true, _) => write!(f, "<{name}>"),
+                    // `[mode:a|b]` would read as alternatives
+                    (false, Variable::Choice(_)) => write!(f, "[<{name}>]"),
+                    (false, _) => write!(f, "[{name}]"),
                 }
             },
             CmdWord::FlagShort{ value, input } => {
@@ -97,31 +254,163 @@ impl fmt::Display for CmdWord {
                 let flag: String = values.iter().collect();
                 write!(f, "-{flag}")
             },
-            CmdWord::BinaryOp{ .. } => {
-                write!(f, "unimplemented")
-            }
+            CmdWord::FlagAlias{ short, long, input } => {
+                if let Some(input) = input.as_ref() {
+                    write!(f, "-{short}/--{long} {input}")
+                } else {
+                    write!(f, "-{short}/--{long}")
+                }
+            },
+            CmdWord::Delimiter => write!(f, "--"),
+            CmdWord::Connector(connector) => write!(f, "{connector}"),
+            CmdWord::Redirect{ op, target } => {
+                if let Some(target) = target.as_ref() {
+                    write!(f, "{op} {target}")
+                } else {
+                    write!(f, "{op}")
+                }
+            },
+            CmdWord::Substitution{ words } => {
+                let words: Vec<String> = words.iter().map(|x| x.to_string()).collect();
+                write!(f, "$({})", words.join(" "))
+            },
+            CmdWord::Composite{ parts } => write!(f, "{}", composite_parts(parts).concat()),
+            // `[name]` is an optional variable, so a literal has to be quoted
+            CmdWord::Optional{ word } => match word.as_ref() {
+                CmdWord::Literal{ value } => write!(f, "[{}]", fmt_literal(value, true)),
+                word => write!(f, "[{word}]"),
+            },
+            CmdWord::Repeated{ word } => write!(f, "{word}..."),
+            CmdWord::BinaryOp{ op: BinaryOp::Or, lhs, rhs } => write!(f, "{lhs} | {rhs}"),
+        }
+    }
+}
+
+/// The text of each part of a composite word, quoted where the parts
+/// would otherwise read as something else.
+pub fn composite_parts(parts: &[CmdWord]) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut prev: Option<&CmdWord> = None;
+    for part in parts {
+        let text = match (prev, part) {
+            // Two literals next to each other would read as one
+            (Some(CmdWord::Literal{..}), CmdWord::Literal{value}) => fmt_literal(value, true),
+            // `<file>..` would read as a repeated variable
+            // and `-<name>` as a flag
+            (prev, CmdWord::Literal{value}) if (prev.is_some() && value.starts_with(".."))
+                || value.starts_with('-') => fmt_literal(value, true),
+            _ => part.to_string(),
+        };
+        res.push(text);
+        prev = Some(part);
+    }
+    res
+}
+
+// Literals are written bare when the lexer reads them back as the same
+// word, otherwise they are quoted.
+fn fmt_literal(value: &str, quote: bool) -> String {
+    let is_bare = !quote
+        && value.chars().all(|c| is_str_letter(Some(c)))
+        && CmdLexer::compile(value) == vec![Token::Str(value.to_string())];
+
+    if is_bare {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Prints the ast as a pattern that compiles back to the same ast.
+pub fn to_pattern(ast: &[CmdWord]) -> String {
+    ast.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// One command of a pipeline or a chain and the connector to the next one.
+#[derive(Debug, PartialEq)]
+pub struct Stage<'a> {
+    pub words: &'a [CmdWord],
+    pub connector: Option<&'a Connector>,
+}
+
+/// Splits the words of the ast into the commands joined by connectors.
+pub fn stages(ast: &[CmdWord]) -> Vec<Stage<'_>> {
+    let mut res = Vec::new();
+    let mut start = 0;
+
+    for (idx, word) in ast.iter().enumerate() {
+        if let CmdWord::Connector(connector) = word {
+            res.push(Stage { words: &ast[start..idx], connector: Some(connector) });
+            start = idx + 1;
         }
     }
+    res.push(Stage { words: &ast[start..], connector: None });
+
+    res
+}
+
+// A variable is written as `name` or `name:type`.
+fn variable(value: &str, required: bool) -> Result<CmdWord, String> {
+    let (name, kind) = match value.split_once(':') {
+        Some((name, kind)) => {
+            let kind = Variable::from_type(kind)
+                .ok_or_else(|| format!("Unknown variable type '{kind}' in <{value}>"))?;
+            (name, kind)
+        },
+        None => (value, Variable::String),
+    };
+    Ok(CmdWord::Variable { name: name.to_string(), kind, required })
+}
+
+// An optional variable is the same as `[name]`, so both parse the same.
+fn optional(word: CmdWord) -> CmdWord {
+    match word {
+        CmdWord::Variable{ name, kind, .. } => CmdWord::Variable { name, kind, required: false },
+        word => CmdWord::Optional { word: Box::new(word) },
+    }
+}
+
+// `[file]...` is the same as `[<file>...]`, the optional goes outside.
+fn repeated(word: CmdWord) -> CmdWord {
+    match word {
+        CmdWord::Variable{ name, kind, required: false } => {
+            let word = CmdWord::Variable { name, kind, required: true };
+            optional(CmdWord::Repeated { word: Box::new(word) })
+        },
+        CmdWord::Optional{ word } => optional(CmdWord::Repeated { word }),
+        word => CmdWord::Repeated { word: Box::new(word) },
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct CmdParser {
-    lexer: Vec<Token>,
+    lexer: Vec<(Token, Span)>,
     curr_position: usize,
     curr_token: Option<Token>,
     peak_token: Option<Token>,
-    errors: Vec<String>,
+    curr_span: Option<Span>,
+    peak_span: Option<Span>,
 }
 
 impl CmdParser {
     pub fn compile(input: &str) -> Vec<CmdWord> {
-        let lexer = CmdLexer::compile(input);
+        Self::try_compile(input).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    pub fn try_compile(input: &str) -> Result<Vec<CmdWord>, ParseError> {
+        Ok(Self::compile_with_spans(input)?.into_iter().map(|(word, _)| word).collect())
+    }
+
+    /// Parses the pattern and keeps where each of the top level words is.
+    pub fn compile_with_spans(input: &str) -> Result<Vec<(CmdWord, Span)>, ParseError> {
+        let lexer = CmdLexer::compile_with_spans(input)?;
         let mut parser =  Self {
             lexer,
             curr_position: 0,
             curr_token: None,
             peak_token: None,
-            errors: Vec::new()
+            curr_span: None,
+            peak_span: None,
         };
 
         parser.next_token();
@@ -129,73 +418,180 @@ impl CmdParser {
 
         let mut ast = Vec::new();
         while parser.curr_token.is_some() {
-            for item in parser.parse_exp() {
-                 // println!("next round {:?}", item);
-                if let Some(exp) = item {
-                     // println!("exp:: {:?}",exp);
-                    ast.push(exp);
-                }
+            let start = parser.curr_span.map(|x| x.start).unwrap_or_default();
+            for exp in parser.parse_exp()?.into_iter().flatten() {
+                let end = parser.curr_span.map(|x| x.end).unwrap_or(start);
+                ast.push((exp, Span { start, end }));
             }
             parser.next_token();
         };
 
-        // println!("AST::: {:?}", ast);
-
-       return ast;
+        Ok(ast)
     }
 
     fn next_token(&mut self) {
         self.curr_token = self.peak_token.take(); 
+        self.curr_span = self.peak_span.take();
         // TODO: this is just not idea way to do it.
-        self.peak_token = self.lexer.get(self.curr_position).map(|x| x.clone());
+        self.peak_token = self.lexer.get(self.curr_position).map(|x| x.0.clone());
+        self.peak_span = self.lexer.get(self.curr_position).map(|x| x.1);
         self.curr_position = self.curr_position + 1;
     }
 
-    fn read_literal(&self) -> CmdWord {
-        if let Some(Token::Str(value)) = &self.curr_token {
-            CmdWord::Literal {
+    // The error points at the current token or at the end of the pattern.
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        let end = self.lexer.last().map(|x| x.1.end).unwrap_or_default();
+        let span = self.curr_span.unwrap_or(Span { start: end, end });
+        ParseError::new(message, span)
+    }
+
+    // There is no whitespace between the current and the next token.
+    fn is_peak_adjacent(&self) -> bool {
+        match (self.curr_span, self.peak_span) {
+            (Some(curr), Some(peak)) => curr.end == peak.start,
+            _ => false
+        }
+    }
+
+    fn parse_variable(&mut self) -> Result<CmdWord, ParseError> {
+        self.next_token();
+        let mut value = if let Some(Token::Str(value)) = &self.curr_token {
+            value.to_string()
+        } else {
+            return Err(self.error("Variable needs a name like '<name>'"));
+        };
+        // The rest of the allowed values `<mode:soft|mixed|hard>`
+        while self.peak_token == Some(Token::Or) {
+            self.next_token();
+            self.next_token();
+            if let Some(Token::Str(choice)) = &self.curr_token {
+                value.push('|');
+                value.push_str(choice);
+            } else {
+                return Err(self.error(format!("Expected a value after '|' in <{value}>")));
+            }
+        }
+        let word = variable(&value, true).map_err(|err| self.error(err))?;
+        self.next_token();
+
+        if self.curr_token != Some(Token::RAr) {
+            return Err(self.error("LAr can only take one argument and needs closing tag '>'"));
+        }
+        Ok(word)
+    }
+
+    // A word is one or more literal and variable parts without any
+    // whitespace between them, like `origin/<branch>` or `"~"<username>`.
+    fn parse_word(&mut self) -> Result<CmdWord, ParseError> {
+        let mut parts = Vec::new();
+
+        loop {
+            match self.curr_token {
+                Some(Token::Str(_) | Token::Quoted(_)) => parts.push(self.read_literal()?),
+                Some(Token::LAr) => parts.push(self.parse_variable()?),
+                _ => return Err(self.error("A word starts with a string or a variable")),
+            }
+
+            let is_part = matches!(self.peak_token, Some(Token::Str(_) | Token::Quoted(_) | Token::LAr));
+            if !(is_part && self.is_peak_adjacent()) {
+                break;
+            }
+            self.next_token();
+        }
+
+        if parts.len() == 1 {
+            Ok(parts.remove(0))
+        } else {
+            Ok(CmdWord::Composite { parts })
+        }
+    }
+
+    fn parse_group_word(&mut self) -> Result<CmdWord, ParseError> {
+        if self.curr_token.is_none() {
+            return Err(self.error("LSq needs closing tag ']'"));
+        }
+        self.parse_exp()?.into_iter().flatten().next().ok_or_else(|| self.error("Group needs a word"))
+    }
+
+    fn read_literal(&self) -> Result<CmdWord, ParseError> {
+        if let Some(Token::Str(value) | Token::Quoted(value)) = &self.curr_token {
+            Ok(CmdWord::Literal {
                 value: value.to_string(),
+            })
+        } else {
+            Err(self.error("Expected a literal"))
+        }
+    }
+
+    // Both `--depth <depth>` and `--depth=<depth>` take the same input.
+    fn parse_flag_input(&mut self) -> Result<Option<CmdWord>, ParseError> {
+        if self.peak_token == Some(Token::Eq) {
+            self.next_token();
+            if self.peak_token != Some(Token::LAr) {
+                return Err(self.error("Flag input after '=' needs to be a variable '<name>'"));
             }
+        }
+
+        if self.peak_token == Some(Token::LAr) {
+            self.next_token();
+            self.parse_word().map(Some)
         } else {
-            panic!("Called parse literal without string");
+            Ok(None)
         }
     }
 
-    fn parse_exp(&mut self) -> Vec<Option<CmdWord>> {
-        let token = self.curr_token.clone().expect("Cluld not find token in the next token");
+    // The current token is the first spelling of the flag and the peak
+    // token is the `/` between the two spellings.
+    fn parse_flag_alias(&mut self) -> Result<CmdWord, ParseError> {
+        let first = self.curr_token.clone().ok_or_else(|| self.error("Flag alias has to start with a flag"))?;
+        self.next_token();
+        self.next_token();
+
+        let (short, long) = match (&first, &self.curr_token) {
+            (Token::FlagShort(short), Some(Token::FlagLong(long))) => (short.clone(), long.clone()),
+            (Token::FlagLong(long), Some(Token::FlagShort(short))) => (short.clone(), long.clone()),
+            _ => return Err(self.error("Flag alias needs a short and a long flag like '-a/--all'")),
+        };
+
+        Ok(CmdWord::FlagAlias {
+            short: short.chars().next().ok_or_else(|| self.error("Short flag has to have a flag name char"))?,
+            long,
+            input: Box::new(self.parse_flag_input()?),
+        })
+    }
+
+    fn parse_exp(&mut self) -> Result<Vec<Option<CmdWord>>, ParseError> {
+        let token = self.curr_token.clone().ok_or_else(|| self.error("Expected a word"))?;
         let mut words = Vec::new();
 
         match token {
-            // It is always literal in this case
-            Token::Str(_) => {
-                words.push(Some(self.read_literal()));
+            // It is a literal or a word made of literal and variable parts
+            Token::Str(_) | Token::Quoted(_) => {
+                words.push(Some(self.parse_word()?));
             },
             // if next token is `=` we have a required input (depth + 1)
             // if next token is `LSq` we have an input optional (depth + 1)
             // if next token is `LAr` we have an input required (depth + 1)
+            Token::FlagShort(_) if self.peak_token == Some(Token::Alias) => {
+                words.push(Some(self.parse_flag_alias()?));
+            },
             Token::FlagShort(val) => { 
-                let mut input: Option<CmdWord> = None;
-
-                if self.peak_token == Some(Token::LAr) {
-                    self.next_token();
-                    input = self.parse_exp().iter().flatten().next().cloned();
-                }
+                let value = val.chars().next().ok_or_else(|| self.error("Short flag has to have a flag name char"))?;
+                let input = self.parse_flag_input()?;
 
                 words.push(Some(CmdWord::FlagShort {
-                    value: val.chars().next().expect("Short flag has to have a flag name char"),
+                    value,
                     input: Box::new(input),
                 }));
             },
             // if next token is `=` we have a required input (depth + 1)
             // if next token is `LSq` we have an input optional (depth + 1)
             // if next token is `LAr` we have an input required (depth + 1)
+            Token::FlagLong(_) if self.peak_token == Some(Token::Alias) => {
+                words.push(Some(self.parse_flag_alias()?));
+            },
             Token::FlagLong(val) => {
-                let mut input: Option<CmdWord> = None;
-
-                if self.peak_token == Some(Token::LAr) {
-                    self.next_token();
-                    input = self.parse_exp().iter().flatten().next().cloned();
-                }
+                let input = self.parse_flag_input()?;
 
                 words.push(Some(CmdWord::FlagLong {
                     value: val.clone(),
@@ -206,7 +602,7 @@ impl CmdParser {
             // it can not have any input
             Token::FlagCombo(val) => {
                 if val.len() < 2 {
-                    panic!("Flag combo does not have enough flags");
+                    return Err(self.error("Flag combo does not have enough flags"));
                 }
                 
                 words.push(Some(CmdWord::FlagCombo {
@@ -215,53 +611,87 @@ impl CmdParser {
             },
             // call self.parse_exp until the next token is RAr
             Token::LAr => {
+                words.push(Some(self.parse_word()?));
+            },
+            // `[name]` is an optional variable, anything else in the brackets
+            // is an optional word or a list of alternatives `[-a | -b]`.
+            Token::LSq => {
                 self.next_token();
-                if let Some(Token::Str(value)) = &self.curr_token {
-                    words.push(Some(CmdWord::Variable {
-                        name: value.to_string(),
-                        kind: Variable::String,
-                        required: true
-                    }));
+                if let (Some(Token::Str(value)), Some(Token::RSq)) = (&self.curr_token, &self.peak_token) {
+                    let word = variable(value, false).map_err(|err| self.error(err))?;
+                    words.push(Some(word));
+                    self.next_token();
                 } else {
-                    panic!("Called parse variable without string");
-                }
-                self.next_token();
+                    let mut word = self.parse_group_word()?;
+                    while self.peak_token == Some(Token::Or) {
+                        self.next_token();
+                        self.next_token();
+                        word = CmdWord::BinaryOp {
+                            op: BinaryOp::Or,
+                            lhs: Box::new(word),
+                            rhs: Box::new(self.parse_group_word()?),
+                        };
+                    }
+                    self.next_token();
 
-                if self.curr_token != Some(Token::RAr) {
-                    panic!("LAr can onyl take one argument and needs closing tag '>' ");
+                    if self.curr_token != Some(Token::RSq) {
+                        return Err(self.error("LSq can only take one word or alternatives and needs closing tag ']'"));
+                    }
+                    words.push(Some(optional(word)));
                 }
             },
-            Token::LSq => {
-                self.next_token();
-                if let Some(Token::Str(value)) = &self.curr_token {
-                    words.push(Some(CmdWord::Variable {
-                        name: value.to_string(),
-                        kind: Variable::String,
-                        required: false
-                    }));
-                } else {
-                    panic!("Called parse variable without string");
+            Token::Delimiter => {
+                words.push(Some(CmdWord::Delimiter));
+            },
+            Token::Connector(connector) => {
+                words.push(Some(CmdWord::Connector(connector)));
+            },
+            Token::Redirect(op) => {
+                let mut target = None;
+                if op.has_target() {
+                    self.next_token();
+                    if self.curr_token.is_none() {
+                        return Err(self.error(format!("Redirect '{}' needs a target", op)));
+                    }
+                    target = self.parse_exp()?.into_iter().flatten().next();
                 }
+                words.push(Some(CmdWord::Redirect {
+                    op,
+                    target: Box::new(target),
+                }));
+            },
+            Token::SubOpen => {
+                let mut inner = Vec::new();
                 self.next_token();
-
-                if self.curr_token != Some(Token::RSq) {
-                    panic!("LSq can only take one argument and needs closing tag ']' ");
+                while self.curr_token != Some(Token::SubClose) {
+                    if self.curr_token.is_none() {
+                        return Err(self.error("Command substitution needs a closing ')'"));
+                    }
+                    inner.extend(self.parse_exp()?.into_iter().flatten());
+                    self.next_token();
                 }
+                words.push(Some(CmdWord::Substitution { words: inner }));
             },
             Token::Or => {
-                // take the previous exp and combine it with the next exp
-                todo!();
+                return Err(self.error("Alternatives have to be inside of '[...]'"));
             },
             Token::Multiple => {
-                // Take the previous exp and turn it into a vector
-                todo!();
+                return Err(self.error("'...' has to follow a word"));
             },
             t => {
-                panic!("Unimplemented token paresr for {:?}", t);
+                return Err(self.error(format!("Unexpected '{t}'")));
             }
         };
 
-        words
+        // `<file>...` repeats the word before
+        if self.peak_token == Some(Token::Multiple) {
+            self.next_token();
+            if let Some(Some(word)) = words.pop() {
+                words.push(Some(repeated(word)));
+            }
+        }
+
+        Ok(words)
     }
 }
 
@@ -419,4 +849,531 @@ mod tests {
                    }
         ]);
     }
+
+    #[test]
+    fn command_with_flag_alias() {
+        let expected = vec![
+                   CmdWord::Literal {
+                       value: "git".to_string(),
+                   },
+                   CmdWord::Literal {
+                       value: "add".to_string(),
+                   },
+                   CmdWord::FlagAlias {
+                       short: 'A',
+                       long: "all".to_string(),
+                       input: Box::new(None),
+                   }
+        ];
+
+        assert_eq!(CmdParser::compile("git add -A/--all"), expected);
+        assert_eq!(CmdParser::compile("git add --all/-A"), expected);
+    }
+
+    #[test]
+    fn flag_alias_with_input() {
+        let parser = CmdParser::compile("head -n/--lines <count>");
+
+        assert_eq!(parser[1], CmdWord::FlagAlias {
+            short: 'n',
+            long: "lines".to_string(),
+            input: Box::new(Some(CmdWord::Variable {
+                name: "count".to_string(),
+                kind: Variable::String,
+                required: true,
+            })),
+        });
+    }
+
+    #[test]
+    fn flag_with_equal_input() {
+        assert_eq!(
+            CmdParser::compile("git clone --depth=<depth>"),
+            CmdParser::compile("git clone --depth <depth>")
+        );
+    }
+
+    #[test]
+    fn command_with_delimiter() {
+        let parser = CmdParser::compile("cargo run -- <arg>");
+
+        assert_eq!(parser[2], CmdWord::Delimiter);
+        assert_eq!(parser.len(), 4);
+    }
+
+    #[test]
+    fn pipeline() {
+        let parser = CmdParser::compile("ps aux | grep <name>");
+
+        assert_eq!(parser[2], CmdWord::Connector(Connector::Pipe));
+
+        let stages = stages(&parser);
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[0].words.len(), 2);
+        assert_eq!(stages[0].connector, Some(&Connector::Pipe));
+        assert_eq!(stages[1].words.len(), 2);
+        assert_eq!(stages[1].connector, None);
+    }
+
+    #[test]
+    fn chain() {
+        let parser = CmdParser::compile("make && make install || echo failed ; ls");
+        let connectors: Vec<_> = stages(&parser).iter().map(|x| x.connector.cloned()).collect();
+
+        assert_eq!(connectors, vec![
+            Some(Connector::And),
+            Some(Connector::Or),
+            Some(Connector::Then),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn redirect() {
+        let parser = CmdParser::compile("ls > <file> 2>&1");
+
+        assert_eq!(parser, vec![
+            CmdWord::Literal {
+                value: "ls".to_string(),
+            },
+            CmdWord::Redirect {
+                op: Redirect::Out,
+                target: Box::new(Some(CmdWord::Variable {
+                    name: "file".to_string(),
+                    kind: Variable::String,
+                    required: true,
+                })),
+            },
+            CmdWord::Redirect {
+                op: Redirect::ErrToOut,
+                target: Box::new(None),
+            },
+        ]);
+    }
+
+    #[test]
+    fn substitution() {
+        let parser = CmdParser::compile("git checkout $(git rev-parse <rev>)");
+
+        assert_eq!(parser[2], CmdWord::Substitution {
+            words: vec![
+                CmdWord::Literal {
+                    value: "git".to_string(),
+                },
+                CmdWord::Literal {
+                    value: "rev-parse".to_string(),
+                },
+                CmdWord::Variable {
+                    name: "rev".to_string(),
+                    kind: Variable::String,
+                    required: true,
+                },
+            ],
+        });
+    }
+
+    #[test]
+    fn composite_words() {
+        let variable = |name: &str| CmdWord::Variable {
+            name: name.to_string(),
+            kind: Variable::String,
+            required: true,
+        };
+        let literal = |value: &str| CmdWord::Literal {
+            value: value.to_string(),
+        };
+
+        assert_eq!(CmdParser::compile("cd \"~\"<username>")[1], CmdWord::Composite {
+            parts: vec![literal("~"), variable("username")],
+        });
+        assert_eq!(CmdParser::compile("git reset HEAD~<n>")[2], CmdWord::Composite {
+            parts: vec![literal("HEAD~"), variable("n")],
+        });
+        assert_eq!(CmdParser::compile("git push origin/<branch>")[2], CmdWord::Composite {
+            parts: vec![literal("origin/"), variable("branch")],
+        });
+        assert_eq!(CmdParser::compile("touch <file>.txt")[1], CmdWord::Composite {
+            parts: vec![variable("file"), literal(".txt")],
+        });
+        assert_eq!(CmdParser::compile("cp <a> <b>").len(), 3);
+    }
+
+    #[test]
+    fn composite_flag_input() {
+        let parser = CmdParser::compile("tar -f <name>.tar");
+
+        assert_eq!(parser[1], CmdWord::FlagShort {
+            value: 'f',
+            input: Box::new(Some(CmdWord::Composite {
+                parts: vec![
+                    CmdWord::Variable {
+                        name: "name".to_string(),
+                        kind: Variable::String,
+                        required: true,
+                    },
+                    CmdWord::Literal {
+                        value: ".tar".to_string(),
+                    },
+                ],
+            })),
+        });
+    }
+
+    #[test]
+    fn optional_literal() {
+        let parser = CmdParser::compile("cd [\"~\"]");
+
+        assert_eq!(parser[1], CmdWord::Optional {
+            word: Box::new(CmdWord::Literal {
+                value: "~".to_string(),
+            }),
+        });
+    }
+
+    #[test]
+    fn optional_variable() {
+        let expected = CmdWord::Variable {
+            name: "dir".to_string(),
+            kind: Variable::String,
+            required: false,
+        };
+
+        assert_eq!(CmdParser::compile("ls [dir]")[1], expected);
+        assert_eq!(CmdParser::compile("ls [<dir>]")[1], expected);
+    }
+
+    #[test]
+    fn optional_flags() {
+        let parser = CmdParser::compile("git reset [-q] [--hard | --soft] [-t <target>]");
+
+        assert_eq!(parser[2], CmdWord::Optional {
+            word: Box::new(CmdWord::FlagShort {
+                value: 'q',
+                input: Box::new(None),
+            }),
+        });
+        assert_eq!(parser[3], CmdWord::Optional {
+            word: Box::new(CmdWord::BinaryOp {
+                op: BinaryOp::Or,
+                lhs: Box::new(CmdWord::FlagLong {
+                    value: "hard".to_string(),
+                    input: Box::new(None),
+                }),
+                rhs: Box::new(CmdWord::FlagLong {
+                    value: "soft".to_string(),
+                    input: Box::new(None),
+                }),
+            }),
+        });
+        assert_eq!(parser[4], CmdWord::Optional {
+            word: Box::new(CmdWord::FlagShort {
+                value: 't',
+                input: Box::new(Some(CmdWord::Variable {
+                    name: "target".to_string(),
+                    kind: Variable::String,
+                    required: true,
+                })),
+            }),
+        });
+    }
+
+    #[test]
+    fn quoted_and_bare_literals() {
+        let literal = |value: &str| CmdWord::Literal {
+            value: value.to_string(),
+        };
+
+        assert_eq!(CmdParser::compile("cd .."), vec![literal("cd"), literal("..")]);
+        assert_eq!(CmdParser::compile("cd /"), vec![literal("cd"), literal("/")]);
+        assert_eq!(CmdParser::compile("cd -"), vec![literal("cd"), literal("-")]);
+        assert_eq!(CmdParser::compile("cd \"..\""), vec![literal("cd"), literal("..")]);
+        assert_eq!(CmdParser::compile("echo \"a | b\""), vec![literal("echo"), literal("a | b")]);
+        assert_eq!(CmdParser::compile("ls -1 ~/v1.2"), vec![
+            literal("ls"),
+            CmdWord::FlagShort {
+                value: '1',
+                input: Box::new(None),
+            },
+            literal("~/v1.2"),
+        ]);
+    }
+
+    #[test]
+    fn typed_variables() {
+        let variable = |name: &str, kind: Variable| CmdWord::Variable {
+            name: name.to_string(),
+            kind,
+            required: true,
+        };
+
+        assert_eq!(CmdParser::compile("head -n <lines:int>")[1], CmdWord::FlagShort {
+            value: 'n',
+            input: Box::new(Some(variable("lines", Variable::Int))),
+        });
+        assert_eq!(CmdParser::compile("cat <path:file>")[1], variable("path", Variable::Path));
+        assert_eq!(CmdParser::compile("git clone <url:url>")[2], variable("url", Variable::Url));
+        assert_eq!(CmdParser::compile("git switch <branch:ref>")[2], variable("branch", Variable::GitRef));
+        // A name alone does not give a type, so stored patterns keep their grading
+        assert_eq!(CmdParser::compile("git clone <url>")[2], variable("url", Variable::String));
+        assert_eq!(Variable::suggest("url"), Some(Variable::Url));
+        assert_eq!(Variable::suggest("message"), None);
+        assert_eq!(CmdParser::compile("sleep <time:duration>")[1], variable("time", Variable::Duration));
+        assert_eq!(CmdParser::compile("echo <message>")[1], variable("message", Variable::String));
+        assert_eq!(CmdParser::compile("ls [dir:path]")[1], CmdWord::Variable {
+            name: "dir".to_string(),
+            kind: Variable::Path,
+            required: false,
+        });
+    }
+
+    #[test]
+    fn choice_variable() {
+        let choice = CmdWord::Variable {
+            name: "mode".to_string(),
+            kind: Variable::Choice(vec!["soft".to_string(), "mixed".to_string(), "hard".to_string()]),
+            required: true,
+        };
+
+        assert_eq!(CmdParser::compile("git reset <mode:soft|mixed|hard>")[2], choice);
+        assert_eq!(CmdParser::compile("git reset <mode:soft | mixed | hard>")[2], choice);
+        assert_eq!(CmdParser::compile("ls --color <when:auto|never>")[1], CmdWord::FlagLong {
+            value: "color".to_string(),
+            input: Box::new(Some(CmdWord::Variable {
+                name: "when".to_string(),
+                kind: Variable::Choice(vec!["auto".to_string(), "never".to_string()]),
+                required: true,
+            })),
+        });
+    }
+
+    #[test]
+    fn display_variables() {
+        assert_eq!(CmdParser::compile("git add <path>")[2].to_string(), "<path>");
+        assert_eq!(CmdParser::compile("ls [dir]")[1].to_string(), "[dir]");
+        assert_eq!(CmdParser::compile("head -n <lines:int>")[1].to_string(), "-n <lines:int>");
+        assert_eq!(CmdParser::compile("ls [<when:auto|never>]")[1].to_string(), "[<when:auto|never>]");
+    }
+
+    #[test]
+    fn pattern_round_trip() {
+        let patterns = [
+            "git commit -m <message>",
+            "cd [\"~\"]",
+            "git reset [--hard | --soft] <commit:ref>",
+            "git reset <mode:soft|mixed|hard>",
+            "ls -la | grep \"a b\" > <file> 2>&1",
+            "git push origin/<branch> --depth <n>",
+            "touch <file>\"..\"txt \"-\"",
+            "kill $(pgrep <name>) && echo \"done \\\"ok\\\"\"",
+            "tail -n/--lines <count> -- [file]",
+        ];
+
+        for pattern in patterns {
+            let ast = CmdParser::compile(pattern);
+            assert_eq!(CmdParser::compile(&to_pattern(&ast)), ast, "{pattern}");
+        }
+        assert_eq!(to_pattern(&CmdParser::compile("cd \"..\"  [ -a ]")), "cd .. [-a]");
+    }
+
+    #[test]
+    fn repeated_words() {
+        let variable = |name: &str, required: bool| CmdWord::Variable {
+            name: name.to_string(),
+            kind: Variable::String,
+            required,
+        };
+        let repeated = |word: CmdWord| CmdWord::Repeated { word: Box::new(word) };
+        let optional = |word: CmdWord| CmdWord::Optional { word: Box::new(word) };
+
+        assert_eq!(CmdParser::compile("git add <path>...")[2], repeated(variable("path", true)));
+        assert_eq!(CmdParser::compile("git add [<path>...]")[2], optional(repeated(variable("path", true))));
+        assert_eq!(CmdParser::compile("git add [path]...")[2], optional(repeated(variable("path", true))));
+        assert_eq!(CmdParser::compile("ssh -v...")[1], repeated(CmdWord::FlagShort {
+            value: 'v',
+            input: Box::new(None),
+        }));
+        assert_eq!(CmdParser::compile("grep -e <pattern>...")[1], repeated(CmdWord::FlagShort {
+            value: 'e',
+            input: Box::new(Some(variable("pattern", true))),
+        }));
+        assert_eq!(CmdParser::compile("git add [<file>... | <dir>...]")[2], optional(CmdWord::BinaryOp {
+            op: BinaryOp::Or,
+            lhs: Box::new(repeated(variable("file", true))),
+            rhs: Box::new(repeated(variable("dir", true))),
+        }));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input: &str| CmdParser::try_compile(input).unwrap_err();
+
+        assert_eq!(error("git add <path").message, "LAr can only take one argument and needs closing tag '>'");
+        assert_eq!(error("git add <path").span, Span { start: 13, end: 13 });
+        assert_eq!(error("git reset [--hard").span, Span { start: 17, end: 17 });
+        assert_eq!(error("ls <n:float>").message, "Unknown variable type 'float' in <n:float>");
+        assert_eq!(error("ls <n:float>").span, Span { start: 4, end: 11 });
+        assert_eq!(error("git add (<path>)").span, Span { start: 8, end: 9 });
+    }
+
+    #[test]
+    fn spans() {
+        let spans: Vec<Span> = CmdParser::compile_with_spans("git add -m <msg> [-a | -b] x")
+            .unwrap()
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect();
+
+        assert_eq!(spans, vec![
+            Span { start: 0, end: 3 },
+            Span { start: 4, end: 7 },
+            Span { start: 8, end: 16 },
+            Span { start: 17, end: 26 },
+            Span { start: 27, end: 28 },
+        ]);
+    }
+
+    mod round_trip {
+        use super::super::*;
+        use proptest::prelude::*;
+
+        fn literal() -> BoxedStrategy<CmdWord> {
+            "[ -~]{1,8}".prop_map(|value| CmdWord::Literal { value }).boxed()
+        }
+
+        fn kind() -> BoxedStrategy<Variable> {
+            prop_oneof![
+                Just(Variable::String),
+                Just(Variable::Int),
+                Just(Variable::Path),
+                Just(Variable::Url),
+                Just(Variable::GitRef),
+                Just(Variable::Duration),
+                prop::collection::vec("[a-z]{1,5}", 2..4).prop_map(Variable::Choice),
+            ].boxed()
+        }
+
+        fn variable(required: bool) -> BoxedStrategy<CmdWord> {
+            ("[a-z][a-z0-9_]{0,6}", kind())
+                .prop_map(move |(name, kind)| CmdWord::Variable { name, kind, required })
+                .boxed()
+        }
+
+        fn composite() -> BoxedStrategy<CmdWord> {
+            prop::collection::vec(prop_oneof![literal(), variable(true)], 2..4)
+                .prop_map(|parts| CmdWord::Composite { parts })
+                .boxed()
+        }
+
+        // Flag inputs always start with a variable
+        fn input() -> BoxedStrategy<Option<CmdWord>> {
+            prop::option::of(prop_oneof![
+                variable(true),
+                (variable(true), composite()).prop_map(|(var, word)| match word {
+                    CmdWord::Composite { mut parts } => {
+                        parts.insert(0, var);
+                        CmdWord::Composite { parts }
+                    },
+                    word => word,
+                }),
+            ]).boxed()
+        }
+
+        fn flag() -> BoxedStrategy<CmdWord> {
+            prop_oneof![
+                ("[a-zA-Z]", input()).prop_map(|(value, input)| CmdWord::FlagShort {
+                    value: value.chars().next().unwrap(),
+                    input: Box::new(input),
+                }),
+                ("[a-z][a-z-]{0,8}", input()).prop_map(|(value, input)| CmdWord::FlagLong {
+                    value,
+                    input: Box::new(input),
+                }),
+                "[a-zA-Z]{2,4}".prop_map(|values| CmdWord::FlagCombo { values: values.chars().collect() }),
+                ("[a-zA-Z]", "[a-z][a-z-]{0,8}", input()).prop_map(|(short, long, input)| CmdWord::FlagAlias {
+                    short: short.chars().next().unwrap(),
+                    long,
+                    input: Box::new(input),
+                }),
+            ].boxed()
+        }
+
+        fn simple_word() -> BoxedStrategy<CmdWord> {
+            prop_oneof![literal(), variable(true), variable(false), composite(), flag()].boxed()
+        }
+
+        fn alternatives() -> BoxedStrategy<CmdWord> {
+            let alternative = || prop_oneof![literal(), variable(true), composite(), flag()];
+            (alternative(), prop::collection::vec(alternative(), 1..3)).prop_map(|(first, rest)| {
+                rest.into_iter().fold(first, |lhs, rhs| CmdWord::BinaryOp {
+                    op: BinaryOp::Or,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                })
+            }).boxed()
+        }
+
+        fn word() -> BoxedStrategy<CmdWord> {
+            let redirect = prop_oneof![
+                Just(Redirect::Out),
+                Just(Redirect::Append),
+                Just(Redirect::In),
+                Just(Redirect::Err),
+                Just(Redirect::ErrToOut),
+            ];
+            let connector = prop_oneof![
+                Just(Connector::Pipe),
+                Just(Connector::And),
+                Just(Connector::Or),
+                Just(Connector::Then),
+            ];
+
+            prop_oneof![
+                4 => simple_word(),
+                1 => Just(CmdWord::Delimiter),
+                1 => connector.prop_map(CmdWord::Connector),
+                1 => (redirect, prop_oneof![literal(), variable(true), composite()]).prop_map(|(op, target)| {
+                    let target = if op.has_target() { Some(target) } else { None };
+                    CmdWord::Redirect { op, target: Box::new(target) }
+                }),
+                1 => prop::collection::vec(simple_word(), 1..3).prop_map(|words| CmdWord::Substitution { words }),
+                1 => prop_oneof![literal(), variable(true), composite(), flag()]
+                    .prop_map(|word| CmdWord::Repeated { word: Box::new(word) }),
+                1 => prop_oneof![literal(), composite(), flag(), alternatives()]
+                    .prop_map(|word| CmdWord::Optional { word: Box::new(word) }),
+            ].boxed()
+        }
+
+        // A flag without input takes the variable after it as its input,
+        // so the parser can never give that ast.
+        fn is_parsable(words: &[CmdWord]) -> bool {
+            let takes_input = |word: &CmdWord| matches!(word,
+                CmdWord::FlagShort { input, .. }
+                | CmdWord::FlagLong { input, .. }
+                | CmdWord::FlagAlias { input, .. } if input.is_none()
+            );
+            fn starts_with_variable(word: &CmdWord) -> bool {
+                match word {
+                    CmdWord::Variable { required, .. } => *required,
+                    CmdWord::Composite { parts } => matches!(parts[0], CmdWord::Variable { .. }),
+                    CmdWord::Repeated { word } => starts_with_variable(word),
+                    _ => false,
+                }
+            }
+            let flat: Vec<&[CmdWord]> = std::iter::once(words)
+                .chain(words.iter().filter_map(|word| match word {
+                    CmdWord::Substitution { words } => Some(words.as_slice()),
+                    _ => None,
+                }))
+                .collect();
+
+            flat.iter().all(|words| words.windows(2).all(|x| !(takes_input(&x[0]) && starts_with_variable(&x[1]))))
+        }
+
+        proptest! {
+            #[test]
+            fn compile_display(words in prop::collection::vec(word(), 1..6).prop_filter("parsable", |x| is_parsable(x))) {
+                prop_assert_eq!(CmdParser::compile(&to_pattern(&words)), words);
+            }
+        }
+    }
 }
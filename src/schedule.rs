@@ -0,0 +1,75 @@
+use std::time::Duration;
+use chrono::NaiveDateTime;
+use crate::parser::compare::Grade;
+
+// A word of the command takes about this long to recall and type. An
+// answer much slower than that was remembered with effort.
+const SECS_PER_WORD: u64 = 3;
+const MIN_SLOW_SECS: u64 = 5;
+
+/// How well a quest was remembered in one answer.
+///
+/// - Strong: right and without thinking about it long
+/// - Weak: right, but slow to come up with or with a hint
+/// - Almost: a near miss, like a typo
+/// - Missed: wrong or out of time
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Recall {
+    Strong,
+    Weak,
+    Almost,
+    Missed,
+}
+
+pub fn is_slow(took: Duration, words: usize) -> bool {
+    took.as_secs() > (SECS_PER_WORD * words as u64).max(MIN_SLOW_SECS)
+}
+
+impl Recall {
+    pub fn of(grade: Grade, took: Duration, words: usize, hints: usize) -> Self {
+        match grade {
+            Grade::Correct if hints > 0 || is_slow(took, words) => Recall::Weak,
+            Grade::Correct => Recall::Strong,
+            Grade::Almost => Recall::Almost,
+            Grade::Wrong => Recall::Missed,
+        }
+    }
+}
+
+/// The days until the quest is asked again and when that is. A strong
+/// answer doubles the wait, a weak one keeps it and a miss asks again in
+/// the next session.
+pub fn next_review(interval_days: i32, recall: Recall, now: NaiveDateTime) -> (i32, NaiveDateTime) {
+    let interval = match recall {
+        Recall::Strong => (interval_days * 2).max(1),
+        Recall::Weak => interval_days.max(1),
+        Recall::Almost => 1,
+        Recall::Missed => 0,
+    };
+    (interval, now + chrono::Duration::days(interval.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_answers_are_weak() {
+        assert_eq!(Recall::of(Grade::Correct, Duration::from_secs(2), 3, 0), Recall::Strong);
+        assert_eq!(Recall::of(Grade::Correct, Duration::from_secs(12), 3, 0), Recall::Weak);
+        assert_eq!(Recall::of(Grade::Correct, Duration::from_secs(6), 1, 0), Recall::Weak);
+        assert_eq!(Recall::of(Grade::Correct, Duration::from_secs(2), 3, 1), Recall::Weak);
+        assert_eq!(Recall::of(Grade::Wrong, Duration::from_secs(1), 3, 0), Recall::Missed);
+    }
+
+    #[test]
+    fn review_intervals() {
+        let now = chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc();
+
+        assert_eq!(next_review(0, Recall::Strong, now).0, 1);
+        assert_eq!(next_review(4, Recall::Strong, now).0, 8);
+        assert_eq!(next_review(4, Recall::Weak, now).0, 4);
+        assert_eq!(next_review(4, Recall::Almost, now).0, 1);
+        assert_eq!(next_review(4, Recall::Missed, now), (0, now));
+    }
+}
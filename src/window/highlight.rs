@@ -0,0 +1,234 @@
+use crossterm::style::Color;
+use crate::parser::lexer::{CmdLexer, Token};
+use crate::parser::parser::{composite_parts, CmdParser, CmdWord};
+use super::{Cell, Theme};
+
+fn push(cells: &mut Vec<Cell>, text: &str, color: Color) {
+    cells.extend(text.chars().map(|ch| Cell::new(ch, color)));
+}
+
+/// The pattern as cells with its words coloured by kind: the commands,
+/// flags, variables and operators apart. The text is the same as
+/// `to_pattern` prints.
+pub fn highlight(words: &[CmdWord], theme: &Theme) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    for (idx, word) in highlight_words(words, theme).into_iter().enumerate() {
+        if idx > 0 {
+            push(&mut cells, " ", theme.literal);
+        }
+        cells.extend(word);
+    }
+    cells
+}
+
+/// The cells of each word of the pattern, e.g. to blank one of them out.
+pub fn highlight_words(words: &[CmdWord], theme: &Theme) -> Vec<Vec<Cell>> {
+    let mut is_command = true;
+    words.iter()
+        .map(|word| {
+            let mut cells = Vec::new();
+            word_cells(&mut cells, word, is_command, theme);
+            is_command = matches!(word, CmdWord::Connector(_));
+            cells
+        })
+        .collect()
+}
+
+fn input_cells(cells: &mut Vec<Cell>, input: &Option<CmdWord>, theme: &Theme) {
+    if let Some(input) = input {
+        push(cells, " ", theme.literal);
+        word_cells(cells, input, false, theme);
+    }
+}
+
+fn word_cells(cells: &mut Vec<Cell>, word: &CmdWord, is_command: bool, theme: &Theme) {
+    match word {
+        CmdWord::Literal{..} if is_command => push(cells, &word.to_string(), theme.command),
+        CmdWord::Literal{..} => push(cells, &word.to_string(), theme.literal),
+        CmdWord::Variable{..} => push(cells, &word.to_string(), theme.variable),
+        CmdWord::FlagShort{ value, input } => {
+            push(cells, &format!("-{value}"), theme.flag);
+            input_cells(cells, input, theme);
+        },
+        CmdWord::FlagLong{ value, input } => {
+            push(cells, &format!("--{value}"), theme.flag);
+            input_cells(cells, input, theme);
+        },
+        CmdWord::FlagAlias{ short, long, input } => {
+            push(cells, &format!("-{short}"), theme.flag);
+            push(cells, "/", theme.syntax);
+            push(cells, &format!("--{long}"), theme.flag);
+            input_cells(cells, input, theme);
+        },
+        CmdWord::FlagCombo{..} | CmdWord::Delimiter => push(cells, &word.to_string(), theme.flag),
+        CmdWord::Connector(_) => push(cells, &word.to_string(), theme.operator),
+        CmdWord::Redirect{ op, target } => {
+            push(cells, &op.to_string(), theme.operator);
+            input_cells(cells, target, theme);
+        },
+        CmdWord::Substitution{ words } => {
+            push(cells, "$(", theme.operator);
+            cells.extend(highlight(words, theme));
+            push(cells, ")", theme.operator);
+        },
+        CmdWord::Composite{ parts } => {
+            for (text, part) in composite_parts(parts).iter().zip(parts) {
+                match part {
+                    // A literal part can be quoted, so it is coloured as written
+                    CmdWord::Literal{..} => push(cells, text, if is_command { theme.command } else { theme.literal }),
+                    part => word_cells(cells, part, false, theme),
+                }
+            }
+        },
+        CmdWord::Optional{ word: inner } => {
+            let text = word.to_string();
+            push(cells, "[", theme.syntax);
+            match inner.as_ref() {
+                CmdWord::Literal{..} => push(cells, &text[1..text.len() - 1], theme.literal),
+                inner => word_cells(cells, inner, false, theme),
+            }
+            push(cells, "]", theme.syntax);
+        },
+        CmdWord::Repeated{ word } => {
+            word_cells(cells, word, is_command, theme);
+            push(cells, "...", theme.syntax);
+        },
+        CmdWord::BinaryOp{ lhs, rhs, .. } => {
+            word_cells(cells, lhs, is_command, theme);
+            push(cells, " | ", theme.syntax);
+            word_cells(cells, rhs, is_command, theme);
+        },
+    }
+}
+
+/// A pattern as it is typed, coloured by its tokens. The text is kept as
+/// it is, so it can be redrawn while typing, and the part that does not
+/// parse is marked with the error colour.
+pub fn highlight_input(input: &str, theme: &Theme) -> Vec<Cell> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut cells: Vec<Cell> = chars.iter().map(|ch| Cell::new(*ch, theme.literal)).collect();
+
+    let error = match CmdLexer::compile_with_spans(input) {
+        Ok(tokens) => {
+            let mut is_command = true;
+            let mut in_variable = false;
+            for (token, span) in tokens {
+                let color = match &token {
+                    Token::LAr => { in_variable = true; theme.variable },
+                    Token::RAr => { in_variable = false; theme.variable },
+                    _ if in_variable => theme.variable,
+                    Token::Str(_) | Token::Quoted(_) if is_command => theme.command,
+                    Token::Str(_) | Token::Quoted(_) => theme.literal,
+                    Token::FlagShort(_) | Token::FlagCombo(_) | Token::FlagLong(_) | Token::Delimiter => theme.flag,
+                    Token::Connector(_) | Token::Redirect(_) | Token::SubOpen | Token::SubClose => theme.operator,
+                    Token::LSq | Token::RSq | Token::Multiple | Token::Or | Token::Alias | Token::Eq => theme.syntax,
+                };
+                for cell in cells.iter_mut().take(span.end).skip(span.start) {
+                    cell.fg = color;
+                }
+                is_command = match token {
+                    Token::Connector(_) | Token::SubOpen => true,
+                    Token::Str(_) | Token::Quoted(_) => false,
+                    _ => is_command,
+                };
+            }
+            CmdParser::try_compile(input).err()
+        },
+        Err(err) => Some(err),
+    };
+
+    if let Some(err) = error {
+        // An error at the end, like a missing `>`, marks the last char
+        let start = err.span.start.min(chars.len().saturating_sub(1));
+        let end = err.span.end.max(start + 1);
+        for cell in cells.iter_mut().take(end).skip(start) {
+            *cell = cell.set_bg(theme.error);
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parser::to_pattern;
+
+    fn text(cells: &[Cell]) -> String {
+        cells.iter().map(|x| x.ch).collect()
+    }
+
+    #[test]
+    fn same_text_as_pattern() {
+        let patterns = [
+            "git switch -c <branch>",
+            "git log -n <n:int> [<path>]...",
+            "tar -xzf <file> -C [dir]",
+            "ls -a/--all | grep \"a b\" && echo done",
+            "git diff HEAD~<count> -- <path> > <file>",
+            "kill $(pgrep <name>)",
+            "git push [\"--force\"] origin",
+            "curl -X GET|POST <url>",
+        ];
+        for pattern in patterns {
+            let ast = CmdParser::compile(pattern);
+            assert_eq!(text(&highlight(&ast, &Theme::default())), to_pattern(&ast), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn colours_by_kind() {
+        let theme = Theme::default();
+        let cells = highlight(&CmdParser::compile("git switch -c <branch> && git push"), &theme);
+        let colour = |offset: usize| cells[offset].fg;
+
+        assert_eq!(colour(0), theme.command);
+        assert_eq!(colour(4), theme.literal);
+        assert_eq!(colour(11), theme.flag);
+        assert_eq!(colour(14), theme.variable);
+        assert_eq!(colour(23), theme.operator);
+        assert_eq!(colour(26), theme.command);
+    }
+
+    #[test]
+    fn colours_typed_input() {
+        let theme = Theme::default();
+        let input = "git  switch -c <branch> [x]";
+        let cells = highlight_input(input, &theme);
+
+        assert_eq!(text(&cells), input);
+        assert_eq!(cells[0].fg, theme.command);
+        assert_eq!(cells[5].fg, theme.literal);
+        assert_eq!(cells[12].fg, theme.flag);
+        assert_eq!(cells[18].fg, theme.variable);
+        assert_eq!(cells[24].fg, theme.syntax);
+        assert!(cells.iter().all(|x| x.bg.is_none()));
+
+        let input = "echo \"日本\" -f <file>";
+        let cells = highlight_input(input, &theme);
+        assert_eq!(text(&cells), input);
+        assert_eq!(cells[0].fg, theme.command);
+        assert_eq!(cells[6].fg, theme.literal);
+        assert_eq!(cells[10].fg, theme.flag);
+        assert_eq!(cells[14].fg, theme.variable);
+        assert!(cells.iter().all(|x| x.bg.is_none()));
+    }
+
+    #[test]
+    fn marks_invalid_input() {
+        let theme = Theme::default();
+
+        let cells = highlight_input("git switch -c <branch", &theme);
+        assert_eq!(cells.last().unwrap().bg, Some(theme.error));
+        assert_eq!(cells[0].bg, None);
+
+        let cells = highlight_input("git commit -m \"fix", &theme);
+        assert_eq!(cells[14].bg, Some(theme.error));
+        assert_eq!(cells[4].bg, None);
+
+        let cells = highlight_input("echo \"éé\" <abcd", &theme);
+        assert_eq!(cells.last().unwrap().bg, Some(theme.error));
+        assert_eq!(cells[6].bg, None);
+        assert_eq!(cells[11].fg, theme.variable);
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fmt;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What a key does in the TUIs.
+///
+/// - up, down, select: move in the lists of `ghi explore` and open the item
+/// - submit: enter the answer or the typed line and go on to the next one
+/// - hint: show the next word of the answer in `ghi run`
+/// - close: leave the summary at the end of `ghi run`
+/// - quit: leave any of them right away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Select,
+    Submit,
+    Hint,
+    Close,
+}
+
+impl Action {
+    const ALL: [Action; 7] = [Action::Quit, Action::Up, Action::Down, Action::Select, Action::Submit, Action::Hint, Action::Close];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Select => "select",
+            Action::Submit => "submit",
+            Action::Hint => "hint",
+            Action::Close => "close",
+        }
+    }
+}
+
+/// The actions of each screen. A key can only do one of them there, and
+/// the ones used while an answer is typed can not be plain letters.
+pub const EXPLORE_ACTIONS: [Action; 4] = [Action::Quit, Action::Up, Action::Down, Action::Select];
+pub const ANSWER_ACTIONS: [Action; 3] = [Action::Quit, Action::Submit, Action::Hint];
+pub const SUMMARY_ACTIONS: [Action; 3] = [Action::Quit, Action::Submit, Action::Close];
+
+/// A key with the modifiers it needs, written like `ctrl-c`, `enter` or `k`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = value.trim();
+        loop {
+            let lower = rest.to_lowercase();
+            if lower.starts_with("ctrl-") && rest.len() > 5 {
+                modifiers |= KeyModifiers::CONTROL;
+            } else if lower.starts_with("alt-") && rest.len() > 4 {
+                modifiers |= KeyModifiers::ALT;
+            } else {
+                break;
+            }
+            rest = &rest[lower.find('-').unwrap_or_default() + 1..];
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "esc" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                _ => return Err(format!(
+                    "unknown key \"{value}\", use a letter or one of enter, tab, esc, backspace, space, up, down, \
+                     left, right with \"ctrl-\" or \"alt-\" before it if needed"
+                )),
+            },
+        };
+        // Control letters come in as the lowercase letter with the modifier
+        let code = match code {
+            KeyCode::Char(ch) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(ch.to_ascii_lowercase()),
+            code => code,
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        // Shift is part of the letter, like `K`, so only these have to match
+        let modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        self.code == event.code && self.modifiers == modifiers
+    }
+
+    // A key that is typed as a letter of the answer
+    fn is_typed(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && self.modifiers.is_empty()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(ch) => write!(f, "{ch}"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Up => write!(f, "Up"),
+            KeyCode::Down => write!(f, "Down"),
+            KeyCode::Left => write!(f, "Left"),
+            KeyCode::Right => write!(f, "Right"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// The keys of the actions, from the `[keys]` config or the defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMap {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let key = |code| Key::new(code, KeyModifiers::NONE);
+        Self {
+            bindings: vec![
+                (Action::Quit, vec![Key::new(KeyCode::Char('c'), KeyModifiers::CONTROL)]),
+                (Action::Up, vec![key(KeyCode::Char('k')), key(KeyCode::Up)]),
+                (Action::Down, vec![key(KeyCode::Char('j')), key(KeyCode::Down)]),
+                (Action::Select, vec![key(KeyCode::Enter)]),
+                (Action::Submit, vec![key(KeyCode::Enter)]),
+                (Action::Hint, vec![key(KeyCode::Tab)]),
+                (Action::Close, vec![key(KeyCode::Char('q'))]),
+            ],
+        }
+    }
+}
+
+impl KeyMap {
+    /// The default keys with the ones of the `[keys]` config instead, e.g.
+    ///
+    /// ```text
+    /// [keys]
+    /// up = ["k", "ctrl-p", "up"]
+    /// hint = "ctrl-h"
+    /// ```
+    pub fn parse(values: &HashMap<String, Vec<String>>) -> Result<Self, String> {
+        let mut keymap = Self::default();
+
+        let mut names: Vec<&String> = values.keys().collect();
+        names.sort();
+        for name in names {
+            let Some(action) = Action::ALL.into_iter().find(|x| x.name() == name) else {
+                let all: Vec<&str> = Action::ALL.iter().map(|x| x.name()).collect();
+                return Err(format!("keys.{name}: unknown action, the actions are {}", all.join(", ")));
+            };
+            if values[name].is_empty() {
+                return Err(format!("keys.{name}: needs at least one key"));
+            }
+            let keys = values[name].iter()
+                .map(|x| Key::parse(x).map_err(|err| format!("keys.{name}: {err}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(entry) = keymap.bindings.iter_mut().find(|(x, _)| *x == action) {
+                entry.1 = keys;
+            }
+        }
+
+        keymap.validate()?;
+        Ok(keymap)
+    }
+
+    fn keys(&self, action: Action) -> &[Key] {
+        self.bindings.iter().find(|(x, _)| *x == action).map(|(_, keys)| keys.as_slice()).unwrap_or_default()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for action in ANSWER_ACTIONS {
+            if let Some(key) = self.keys(action).iter().find(|x| x.is_typed()) {
+                return Err(format!(
+                    "keys.{}: \"{key}\" would be typed into the answer, use a key like \"tab\" or \"ctrl-{key}\"",
+                    action.name()
+                ));
+            }
+        }
+
+        for actions in [&EXPLORE_ACTIONS[..], &ANSWER_ACTIONS[..], &SUMMARY_ACTIONS[..]] {
+            for (idx, action) in actions.iter().enumerate() {
+                for other in &actions[idx + 1..] {
+                    if let Some(key) = self.keys(*action).iter().find(|x| self.keys(*other).contains(x)) {
+                        return Err(format!(
+                            "keys.{}: \"{key}\" is also the key of {}, they are used on the same screen",
+                            other.name(),
+                            action.name()
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The action of the key on a screen with these actions.
+    pub fn action(&self, event: &KeyEvent, actions: &[Action]) -> Option<Action> {
+        actions.iter().copied().find(|action| self.keys(*action).iter().any(|x| x.matches(event)))
+    }
+
+    /// The keys of each action by its name, as they can be written in the
+    /// `[keys]` config.
+    pub fn bindings(&self) -> Vec<(&'static str, Vec<String>)> {
+        self.bindings.iter().map(|(action, keys)| (action.name(), keys.iter().map(|x| x.to_string()).collect())).collect()
+    }
+
+    /// The first key of the action, to show it on the screen.
+    pub fn label(&self, action: Action) -> String {
+        self.keys(action).first().map(|x| x.to_string()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(values: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        values.iter().map(|(name, keys)| (name.to_string(), keys.iter().map(|x| x.to_string()).collect())).collect()
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(Key::parse("ctrl-C"), Ok(Key::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert_eq!(Key::parse("alt-ctrl-x"), Ok(Key::new(KeyCode::Char('x'), KeyModifiers::CONTROL | KeyModifiers::ALT)));
+        assert_eq!(Key::parse("Enter"), Ok(Key::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(Key::parse("-"), Ok(Key::new(KeyCode::Char('-'), KeyModifiers::NONE)));
+        assert!(Key::parse("ctrl-").is_err());
+        assert!(Key::parse("pageup").is_err());
+        assert_eq!(Key::parse("ctrl-h").unwrap().to_string(), "Ctrl-h");
+
+        let bindings = KeyMap::default().bindings().into_iter().map(|(name, keys)| (name.to_string(), keys)).collect();
+        assert_eq!(KeyMap::parse(&bindings), Ok(KeyMap::default()));
+    }
+
+    #[test]
+    fn actions_by_screen() {
+        let keymap = KeyMap::parse(&keys(&[("up", &["ctrl-p", "up"]), ("hint", &["ctrl-h"])])).unwrap();
+        let press = |code, modifiers| KeyEvent::new(code, modifiers);
+
+        assert_eq!(keymap.action(&press(KeyCode::Char('p'), KeyModifiers::CONTROL), &EXPLORE_ACTIONS), Some(Action::Up));
+        assert_eq!(keymap.action(&press(KeyCode::Char('k'), KeyModifiers::NONE), &EXPLORE_ACTIONS), None);
+        assert_eq!(keymap.action(&press(KeyCode::Enter, KeyModifiers::NONE), &EXPLORE_ACTIONS), Some(Action::Select));
+        assert_eq!(keymap.action(&press(KeyCode::Enter, KeyModifiers::NONE), &ANSWER_ACTIONS), Some(Action::Submit));
+        assert_eq!(keymap.action(&press(KeyCode::Char('h'), KeyModifiers::CONTROL), &ANSWER_ACTIONS), Some(Action::Hint));
+        assert_eq!(keymap.action(&press(KeyCode::Char('Q'), KeyModifiers::SHIFT), &SUMMARY_ACTIONS), None);
+        assert_eq!(keymap.label(Action::Hint), "Ctrl-h");
+    }
+
+    #[test]
+    fn invalid_keys() {
+        let err = KeyMap::parse(&keys(&[("jump", &["g"])])).unwrap_err();
+        assert!(err.starts_with("keys.jump: unknown action"), "{err}");
+
+        let err = KeyMap::parse(&keys(&[("hint", &["h"])])).unwrap_err();
+        assert!(err.starts_with("keys.hint: \"h\" would be typed into the answer"), "{err}");
+
+        let err = KeyMap::parse(&keys(&[("down", &["k"])])).unwrap_err();
+        assert!(err.starts_with("keys.down: \"k\" is also the key of up"), "{err}");
+
+        let err = KeyMap::parse(&keys(&[("quit", &["ctlr-c"])])).unwrap_err();
+        assert!(err.starts_with("keys.quit: unknown key \"ctlr-c\""), "{err}");
+    }
+}
@@ -1,7 +1,13 @@
 mod screen_buffer;
 mod rect;
 mod screen;
+mod highlight;
+mod theme;
+mod keys;
 
 pub use screen_buffer::*;
 pub use rect::*;
 pub use screen::*;
+pub use highlight::*;
+pub use theme::*;
+pub use keys::*;
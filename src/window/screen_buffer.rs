@@ -132,6 +132,17 @@ impl ScreenBuf {
         Ok(())
     }
 
+    /// Prints the rows as the output of a command instead of a full screen.
+    /// The blanks at the end of a row are left out.
+    pub fn print(&self, out: &mut impl Write, color: bool) -> io::Result<()> {
+        let width: usize = self.width.into();
+        for row in self.cells.chunks(width) {
+            print_cells(out, row, color)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
     fn reset(&self, out: &mut impl Write) -> io::Result<()> {
         out.queue(Clear(ClearType::All))?;
         out.queue(cursor::MoveTo(0, 0))?;
@@ -155,4 +166,33 @@ pub fn apply_patches(out: &mut impl QueueableCommand, diff: &Vec<Patch>) -> io::
     Ok(())
 }
 
+/// Prints one line of cells and a newline, like `ScreenBuf::print` does
+/// for each row, without the blanks at the end.
+pub fn print_cells(out: &mut impl Write, cells: &[Cell], color: bool) -> io::Result<()> {
+    let len = cells.iter().rposition(|x| *x != Cell::default()).map_or(0, |x| x + 1);
+    queue_cells(out, &cells[..len], color)?;
+    out.queue(style::Print('\n'))?;
+    Ok(())
+}
 
+/// Prints the cells where the cursor is, e.g. to redraw a line while it
+/// is typed.
+pub fn queue_cells(out: &mut impl Write, cells: &[Cell], color: bool) -> io::Result<()> {
+    let mut prev: Option<(style::Color, Option<style::Color>)> = None;
+    for Cell{ch, fg, bg} in cells {
+        if color && prev != Some((*fg, *bg)) {
+            prev = Some((*fg, *bg));
+            if let Some(bg) = bg {
+                out.queue(style::SetBackgroundColor(*bg))?;
+            } else {
+                out.queue(style::ResetColor)?;
+            }
+            out.queue(style::SetForegroundColor(*fg))?;
+        }
+        out.queue(style::Print(ch))?;
+    }
+    if color {
+        out.queue(style::ResetColor)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use crossterm::style::Color;
+
+/// The colours of everything ghi draws, in the TUIs and in the printed
+/// output.
+///
+/// - text, muted: the plain text and the less important parts of it
+/// - good, warn, error: how well something went, like a grade or an accuracy
+/// - bar_fg, bar_bg: the bar with the keys at the bottom of a screen
+/// - command .. syntax: the words of a pattern by kind, where syntax is
+///   the brackets, dots and bars of the pattern syntax
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub text: Color,
+    pub muted: Color,
+    pub good: Color,
+    pub warn: Color,
+    pub error: Color,
+    pub bar_fg: Color,
+    pub bar_bg: Color,
+    pub command: Color,
+    pub literal: Color,
+    pub flag: Color,
+    pub variable: Color,
+    pub operator: Color,
+    pub syntax: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            text: Color::White,
+            muted: Color::DarkGrey,
+            good: Color::Green,
+            warn: Color::Yellow,
+            error: Color::Red,
+            bar_fg: Color::Black,
+            bar_bg: Color::White,
+            command: Color::Cyan,
+            literal: Color::White,
+            flag: Color::Green,
+            variable: Color::Yellow,
+            operator: Color::Magenta,
+            syntax: Color::DarkGrey,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            text: Color::Black,
+            muted: Color::Grey,
+            good: Color::DarkGreen,
+            warn: Color::DarkYellow,
+            error: Color::DarkRed,
+            bar_fg: Color::White,
+            bar_bg: Color::Black,
+            command: Color::DarkBlue,
+            literal: Color::Black,
+            flag: Color::DarkGreen,
+            variable: Color::DarkMagenta,
+            operator: Color::DarkCyan,
+            syntax: Color::Grey,
+        }
+    }
+
+    /// The terminal's own colours everywhere, for `NO_COLOR`.
+    pub fn plain() -> Self {
+        Self {
+            text: Color::Reset,
+            muted: Color::Reset,
+            good: Color::Reset,
+            warn: Color::Reset,
+            error: Color::Reset,
+            bar_fg: Color::Reset,
+            bar_bg: Color::Reset,
+            command: Color::Reset,
+            literal: Color::Reset,
+            flag: Color::Reset,
+            variable: Color::Reset,
+            operator: Color::Reset,
+            syntax: Color::Reset,
+        }
+    }
+
+    fn preset(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "plain" => Some(Self::plain()),
+            _ => None,
+        }
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "text" => Some(&mut self.text),
+            "muted" => Some(&mut self.muted),
+            "good" => Some(&mut self.good),
+            "warn" => Some(&mut self.warn),
+            "error" => Some(&mut self.error),
+            "bar_fg" => Some(&mut self.bar_fg),
+            "bar_bg" => Some(&mut self.bar_bg),
+            "command" => Some(&mut self.command),
+            "literal" => Some(&mut self.literal),
+            "flag" => Some(&mut self.flag),
+            "variable" => Some(&mut self.variable),
+            "operator" => Some(&mut self.operator),
+            "syntax" => Some(&mut self.syntax),
+            _ => None,
+        }
+    }
+
+    /// The theme of the `[theme]` config: a preset with some of its
+    /// colours changed.
+    ///
+    /// ```text
+    /// [theme]
+    /// preset = "light"
+    /// command = "dark_blue"
+    /// variable = "#af5f00"
+    /// ```
+    pub fn parse(values: &HashMap<String, String>) -> Result<Self, String> {
+        let mut theme = match values.get("preset") {
+            Some(name) => Self::preset(name)
+                .ok_or_else(|| format!("theme.preset: unknown preset \"{name}\", use \"dark\", \"light\" or \"plain\""))?,
+            None => Self::default(),
+        };
+
+        let mut names: Vec<&String> = values.keys().filter(|x| *x != "preset").collect();
+        names.sort();
+        for name in names {
+            let Some(slot) = theme.color_mut(name) else {
+                return Err(format!(
+                    "theme.{name}: unknown colour, the colours are text, muted, good, warn, error, bar_fg, bar_bg, \
+                     command, literal, flag, variable, operator and syntax"
+                ));
+            };
+            *slot = parse_color(&values[name]).map_err(|err| format!("theme.{name}: {err}"))?;
+        }
+
+        Ok(theme)
+    }
+}
+
+/// A colour by its name like `dark_grey`, as `#rrggbb` or as an ANSI
+/// number from 0 to 255. `default` is the terminal's own colour.
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    let value = value.trim().to_lowercase().replace('-', "_");
+    if value == "default" || value == "reset" {
+        return Ok(Color::Reset);
+    }
+    if let Ok(color) = Color::try_from(value.as_str()) {
+        return Ok(color);
+    }
+    if let Some(hex) = value.strip_prefix('#').filter(|x| x.len() == 6) {
+        let channel = |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16);
+        if let (Ok(r), Ok(g), Ok(b)) = (channel(0), channel(2), channel(4)) {
+            return Ok(Color::Rgb { r, g, b });
+        }
+    }
+    if let Ok(ansi) = value.parse::<u8>() {
+        return Ok(Color::AnsiValue(ansi));
+    }
+
+    Err(format!("unknown colour \"{value}\", use a name like \"cyan\" or \"dark_grey\", \"#rrggbb\" or a number from 0 to 255"))
+}
+
+/// `NO_COLOR` is set to anything but an empty value, see https://no-color.org
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(parse_color("Dark-Grey"), Ok(Color::DarkGrey));
+        assert_eq!(parse_color("#5fafd7"), Ok(Color::Rgb { r: 0x5f, g: 0xaf, b: 0xd7 }));
+        assert_eq!(parse_color("208"), Ok(Color::AnsiValue(208)));
+        assert_eq!(parse_color("default"), Ok(Color::Reset));
+        assert!(parse_color("#5fafd").is_err());
+        assert!(parse_color("256").is_err());
+        assert!(parse_color("blurple").is_err());
+    }
+
+    #[test]
+    fn parse_theme() {
+        let values = HashMap::from([
+            ("preset".to_string(), "light".to_string()),
+            ("command".to_string(), "blue".to_string()),
+        ]);
+        let theme = Theme::parse(&values).unwrap();
+        assert_eq!(theme.command, Color::Blue);
+        assert_eq!(theme.text, Theme::light().text);
+
+        let values = HashMap::from([("preset".to_string(), "solarized".to_string())]);
+        let err = Theme::parse(&values).unwrap_err();
+        assert!(err.starts_with("theme.preset: unknown preset \"solarized\""), "{err}");
+
+        let values = HashMap::from([("flags".to_string(), "blue".to_string())]);
+        let err = Theme::parse(&values).unwrap_err();
+        assert!(err.starts_with("theme.flags: unknown colour"), "{err}");
+
+        let values = HashMap::from([("flag".to_string(), "bleu".to_string())]);
+        let err = Theme::parse(&values).unwrap_err();
+        assert!(err.starts_with("theme.flag: unknown colour \"bleu\""), "{err}");
+    }
+}